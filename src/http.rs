@@ -0,0 +1,143 @@
+use clap::Args;
+use poem::{http::HeaderValue, middleware::Cors};
+
+/// Where the HTTP modes listen and which browser origins may call them
+#[derive(Args)]
+pub struct HttpArgs {
+    /// Address to listen on, e.g. `0.0.0.0` inside a container
+    #[arg(long, global = true, env = "TSRS_HOST", default_value = "127.0.0.1")]
+    host: String,
+    /// Port to listen on
+    #[arg(long, global = true, env = "TSRS_PORT", default_value_t = 8999)]
+    port: u16,
+    /// Path the MCP endpoint is mounted at, e.g. `/tushare` behind a reverse proxy
+    #[arg(long, global = true, env = "TSRS_PATH", default_value = "/")]
+    path: String,
+    /// Path of the event stream in SSE mode
+    #[arg(long, global = true, env = "TSRS_SSE_PATH", default_value = "/sse")]
+    sse_path: String,
+    /// Path clients post their messages to in SSE mode
+    #[arg(
+        long,
+        global = true,
+        env = "TSRS_MESSAGE_PATH",
+        default_value = "/message"
+    )]
+    message_path: String,
+    /// Browser origins allowed by CORS, `*` for any (defaults to the listen address)
+    #[arg(
+        long = "cors-origin",
+        global = true,
+        env = "TSRS_CORS_ORIGINS",
+        value_delimiter = ','
+    )]
+    cors_origins: Vec<String>,
+}
+
+impl HttpArgs {
+    /// The `host:port` to bind
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// The endpoint path with a leading and without a trailing slash
+    pub fn path(&self) -> String {
+        normalize_path(&self.path)
+    }
+
+    /// The SSE event stream and message paths, which must differ from [`Self::path`]
+    pub fn sse_paths(&self) -> std::io::Result<(String, String)> {
+        let sse_path = normalize_path(&self.sse_path);
+        let message_path = normalize_path(&self.message_path);
+        let path = self.path();
+        if sse_path == message_path || sse_path == path || message_path == path {
+            return Err(std::io::Error::other(format!(
+                "the SSE path '{}', message path '{}' and path '{}' must all differ",
+                sse_path, message_path, path
+            )));
+        }
+        Ok((sse_path, message_path))
+    }
+
+    /// The CORS policy for the configured origins
+    ///
+    /// Without `--cors-origin` only pages served from the listen address itself
+    /// are allowed; entries containing `*` are matched as wildcards.
+    pub fn cors(&self) -> std::io::Result<Cors> {
+        let mut cors = Cors::new().expose_header("Mcp-Session-Id");
+        if self.cors_origins.iter().any(|origin| origin == "*") {
+            return Ok(cors);
+        }
+
+        let origins = if self.cors_origins.is_empty() {
+            self.default_origins()
+        } else {
+            self.cors_origins.clone()
+        };
+        for origin in origins {
+            if origin.contains('*') {
+                cors = cors.allow_origin_regex(origin);
+            } else {
+                let origin = HeaderValue::try_from(origin.as_str()).map_err(|_| {
+                    std::io::Error::other(format!("invalid CORS origin '{}'", origin))
+                })?;
+                cors = cors.allow_origin(origin);
+            }
+        }
+        Ok(cors)
+    }
+
+    fn default_origins(&self) -> Vec<String> {
+        match self.host.as_str() {
+            "127.0.0.1" | "localhost" | "0.0.0.0" | "::" | "::1" => ["127.0.0.1", "localhost"]
+                .iter()
+                .map(|host| format!("http://{}:{}", host, self.port))
+                .collect(),
+            host => vec![format!("http://{}:{}", host, self.port)],
+        }
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        http: HttpArgs,
+    }
+
+    fn parse(args: &[&str]) -> HttpArgs {
+        Cli::parse_from(std::iter::once("tsrs-mcp-server").chain(args.iter().copied())).http
+    }
+
+    #[test]
+    fn test_http_args() {
+        let http = parse(&["--host", "0.0.0.0", "--port", "9000", "--path", "tushare/"]);
+        assert_eq!(http.addr(), "0.0.0.0:9000");
+        assert_eq!(http.path(), "/tushare");
+        assert_eq!(
+            http.default_origins(),
+            ["http://127.0.0.1:9000", "http://localhost:9000"]
+        );
+
+        let http = parse(&["--cors-origin", "https://a.example,https://*.b.example"]);
+        assert_eq!(http.path(), "/");
+        assert_eq!(
+            http.sse_paths().unwrap(),
+            ("/sse".to_string(), "/message".to_string())
+        );
+        assert_eq!(http.cors_origins.len(), 2);
+        assert!(http.cors().is_ok());
+
+        assert!(parse(&["--cors-origin", "bad\norigin"]).cors().is_err());
+        assert!(parse(&["--path", "/sse/"]).sse_paths().is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::{io::IsTerminal, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{EnvFilter, fmt::writer::BoxMakeWriter};
+
+/// Logging options
+///
+/// Logs never go to stdout, which carries the JSON-RPC stream in stdio mode.
+#[derive(Args)]
+pub struct LogArgs {
+    /// Log filter, e.g. `debug` or `info,ts_model=debug` (defaults to `RUST_LOG`, then `info`)
+    #[arg(long, global = true, env = "TSRS_LOG_LEVEL")]
+    log_level: Option<String>,
+    /// Log line format
+    #[arg(long, global = true, env = "TSRS_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// Write logs to this file instead of stderr, rotated as set by `--log-rotation`
+    #[arg(long, global = true, env = "TSRS_LOG_FILE")]
+    log_file: Option<PathBuf>,
+    /// How often the log file is rotated
+    #[arg(long, global = true, env = "TSRS_LOG_ROTATION", value_enum, default_value_t = LogRotation::Daily)]
+    log_rotation: LogRotation,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum LogRotation {
+    /// Start a new file every hour
+    Hourly,
+    /// Start a new file every day
+    Daily,
+    /// Always append to the same file
+    Never,
+}
+
+/// Install the global subscriber
+///
+/// The returned guard flushes the log file when dropped, so it must be kept
+/// alive until the server exits.
+pub fn init(args: &LogArgs) -> std::io::Result<Option<WorkerGuard>> {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level).map_err(std::io::Error::other)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let (writer, ansi, guard) = match &args.log_file {
+        Some(path) => {
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| ".".as_ref());
+            let file_name = path.file_name().ok_or_else(|| {
+                std::io::Error::other(format!("invalid log file '{}'", path.display()))
+            })?;
+            let appender = match args.log_rotation {
+                LogRotation::Hourly => rolling::hourly(dir, file_name),
+                LogRotation::Daily => rolling::daily(dir, file_name),
+                LogRotation::Never => rolling::never(dir, file_name),
+            };
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (BoxMakeWriter::new(writer), false, Some(guard))
+        }
+        None => (
+            BoxMakeWriter::new(std::io::stderr),
+            std::io::stderr().is_terminal(),
+            None,
+        ),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match args.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+    Ok(guard)
+}
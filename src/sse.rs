@@ -0,0 +1,264 @@
+//! The legacy HTTP+SSE transport (MCP 2024-11-05)
+//!
+//! A client opens a `GET` event stream and receives an `endpoint` event naming
+//! the URL to `POST` its JSON-RPC messages to. Responses are not returned from
+//! the `POST`, which is answered with `202 Accepted`, but sent as `message`
+//! events on the stream. Each stream is a session with its own `McpServer`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedSender},
+    stream,
+};
+use poem::{
+    EndpointExt, IntoEndpoint, IntoResponse, Request, get, handler,
+    http::StatusCode,
+    post,
+    web::{
+        Data, Json, Query,
+        sse::{Event, SSE},
+    },
+};
+use poem_mcpserver::{McpServer, protocol::rpc::BatchRequest, tool::Tools};
+use serde::Deserialize;
+
+/// How often an idle event stream sends a keep-alive comment
+///
+/// A disconnected client is only noticed when writing to its stream fails, so
+/// this also bounds how long a closed session lingers.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type ServerFactoryFn<ToolsType> = Box<dyn Fn(&Request) -> McpServer<ToolsType> + Send + Sync>;
+
+struct Session<ToolsType> {
+    server: Arc<tokio::sync::Mutex<McpServer<ToolsType>>>,
+    /// Sends a serialized response to the session's event stream
+    events: UnboundedSender<String>,
+}
+
+struct State<ToolsType> {
+    server_factory: ServerFactoryFn<ToolsType>,
+    message_path: String,
+    sessions: Mutex<HashMap<String, Session<ToolsType>>>,
+}
+
+/// Removes a session once its event stream is dropped
+struct SessionGuard<ToolsType> {
+    state: Arc<State<ToolsType>>,
+    session_id: String,
+}
+
+impl<ToolsType> Drop for SessionGuard<ToolsType> {
+    fn drop(&mut self) {
+        self.state.sessions.lock().unwrap().remove(&self.session_id);
+        tracing::info!(session_id = self.session_id, "closed SSE session");
+    }
+}
+
+#[derive(Deserialize)]
+struct MessageQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+#[handler]
+fn sse_handler<ToolsType>(data: Data<&Arc<State<ToolsType>>>, request: &Request) -> SSE
+where
+    ToolsType: Tools + Send + Sync + 'static,
+{
+    let state = data.0.clone();
+    let session_id = format!("{:032x}", fastrand::u128(..));
+    let (events, receiver) = mpsc::unbounded();
+    state.sessions.lock().unwrap().insert(
+        session_id.clone(),
+        Session {
+            server: Arc::new(tokio::sync::Mutex::new((state.server_factory)(request))),
+            events,
+        },
+    );
+    tracing::info!(session_id, "created SSE session");
+
+    let endpoint = format!(
+        "{}{}?sessionId={}",
+        base_path(request),
+        state.message_path,
+        session_id
+    );
+    let guard = SessionGuard { state, session_id };
+    let stream = stream::once(async move { Event::message(endpoint).event_type("endpoint") })
+        .chain(receiver.map(|response| Event::message(response).event_type("message")))
+        .map(move |event| {
+            let _guard = &guard;
+            event
+        });
+    SSE::new(stream).keep_alive(KEEP_ALIVE)
+}
+
+/// The path prefix the client reached this server under
+///
+/// Made of the `X-Forwarded-Prefix` set by a reverse proxy, followed by the
+/// prefix of any `Route::nest` the endpoints are mounted in. Both are empty
+/// when the server is reached directly.
+fn base_path(request: &Request) -> String {
+    let forwarded = request
+        .header("X-Forwarded-Prefix")
+        .map(|prefix| prefix.trim_end_matches('/'))
+        .filter(|prefix| prefix.starts_with('/'))
+        .unwrap_or_default();
+    let nested = request
+        .original_uri()
+        .path()
+        .strip_suffix(request.uri().path())
+        .unwrap_or_default();
+    format!("{}{}", forwarded, nested.trim_end_matches('/'))
+}
+
+#[handler]
+async fn message_handler<ToolsType>(
+    data: Data<&Arc<State<ToolsType>>>,
+    Query(query): Query<MessageQuery>,
+    batch_request: Json<BatchRequest>,
+) -> impl IntoResponse
+where
+    ToolsType: Tools + Send + Sync + 'static,
+{
+    let (server, events) = {
+        let sessions = data.0.sessions.lock().unwrap();
+        let Some(session) = sessions.get(&query.session_id) else {
+            return StatusCode::NOT_FOUND;
+        };
+        (session.server.clone(), session.events.clone())
+    };
+
+    let session_id = query.session_id;
+    tokio::spawn(async move {
+        for request in batch_request.0 {
+            tracing::info!(session_id, request = ?request, "received request");
+            let Some(resp) = server.lock().await.handle_request(request).await else {
+                continue;
+            };
+            tracing::info!(session_id, response = ?resp, "sending response");
+            let resp = serde_json::to_string(&resp).expect("BUG: serialize response");
+            if events.unbounded_send(resp).is_err() {
+                break;
+            }
+        }
+    });
+    StatusCode::ACCEPTED
+}
+
+/// The event stream and message endpoints of the SSE transport
+///
+/// Mount the first at the SSE path and the second at `message_path` of the same
+/// route. Clients are sent `message_path` behind the prefix they reached the
+/// event stream under, see [`base_path`].
+pub fn endpoints<F, ToolsType>(
+    message_path: impl Into<String>,
+    server_factory: F,
+) -> (impl IntoEndpoint, impl IntoEndpoint)
+where
+    F: Fn(&Request) -> McpServer<ToolsType> + Send + Sync + 'static,
+    ToolsType: Tools + Send + Sync + 'static,
+{
+    let state = Arc::new(State {
+        server_factory: Box::new(server_factory),
+        message_path: message_path.into(),
+        sessions: Default::default(),
+    });
+
+    (
+        get(sse_handler::<ToolsType>::default()).data(state.clone()),
+        post(message_handler::<ToolsType>::default()).data(state),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::{
+        Route, Server,
+        listener::{Acceptor, Listener, TcpListener},
+        test::TestClient,
+    };
+    use poem_mcpserver::tool::NoTools;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sse_session() {
+        let (sse, message) = endpoints("/message", |_| McpServer::new().tools(NoTools));
+        let client = TestClient::new(Route::new().at("/sse", sse).at("/message", message));
+
+        let response = client.get("/sse").send().await;
+        response.assert_status_is_ok();
+        let mut events = response.sse_stream();
+        let Some(Event::Message { event, data, .. }) = events.next().await else {
+            panic!("expected the endpoint event");
+        };
+        assert_eq!(event, "endpoint");
+        let session_id = data.strip_prefix("/message?sessionId=").unwrap();
+
+        client
+            .post(format!("/message?sessionId={session_id}"))
+            .body_json(&json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}))
+            .send()
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+        let Some(Event::Message { event, data, .. }) = events.next().await else {
+            panic!("expected the ping response");
+        };
+        assert_eq!(event, "message");
+        let resp: serde_json::Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(resp["id"], 1);
+
+        client
+            .post("/message?sessionId=unknown")
+            .body_json(&json!({"jsonrpc": "2.0", "id": 2, "method": "ping"}))
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_behind_prefix() {
+        // The nesting prefix is only known from the URI a real server received
+        let (sse, message) = endpoints("/message", |_| McpServer::new().tools(NoTools));
+        let app = Route::new().nest("/mcp", Route::new().at("/sse", sse).at("/message", message));
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+
+        // The path of the `endpoint` event sent to a new event stream
+        let endpoint_path = |forwarded_prefix: Option<&'static str>| async move {
+            let mut request = reqwest::Client::new().get(format!("http://{addr}/mcp/sse"));
+            if let Some(prefix) = forwarded_prefix {
+                request = request.header("X-Forwarded-Prefix", prefix);
+            }
+            let mut response = request.send().await.unwrap();
+            let chunk = response.chunk().await.unwrap().unwrap();
+            let event = String::from_utf8(chunk.to_vec()).unwrap();
+            let data = event
+                .lines()
+                .find_map(|line| line.strip_prefix("data: "))
+                .unwrap();
+            data.split_once('?').unwrap().0.to_string()
+        };
+
+        assert_eq!(endpoint_path(None).await, "/mcp/message");
+        assert_eq!(
+            endpoint_path(Some("/tushare/")).await,
+            "/tushare/mcp/message"
+        );
+        // Only absolute prefixes are used
+        assert_eq!(endpoint_path(Some("evil")).await, "/mcp/message");
+    }
+}
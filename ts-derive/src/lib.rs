@@ -2,7 +2,7 @@
 
 use darling::FromMeta;
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{parse_macro_input, Data, DeriveInput, Fields, LitInt, Type};
 
 /// Options for the TsEndpoint derive macro
@@ -15,6 +15,21 @@ struct EndpointOpts {
     /// Response type (optional)
     #[darling(default)]
     resp: Option<syn::Path>,
+    /// Rows per page for `execute_all` (optional)
+    #[darling(default)]
+    page_size: Option<usize>,
+    /// Calls per minute allowed for the API (optional)
+    #[darling(default)]
+    rate_limit: Option<u32>,
+    /// Retries of transient failures unless the client sets a retry policy (optional)
+    #[darling(default)]
+    retry: Option<u32>,
+    /// Leave out params that serialize to an empty string
+    #[darling(default)]
+    skip_empty: bool,
+    /// Register the endpoint as an MCP tool with this name (requires `resp`)
+    #[darling(default)]
+    tool: Option<String>,
 }
 
 /// Options for the TsResponse derive macro
@@ -22,12 +37,180 @@ struct EndpointOpts {
 struct ResponseOpts {
     /// API name for the Tushare response
     api: String,
+    /// Use the `#[ts_field(index)]` when a column is not listed in `data.fields`
+    #[darling(default)]
+    index_fallback: bool,
+    /// Treat every field as `#[serde(default)]`: missing columns and nulls become `Default::default()`
+    #[darling(default)]
+    default_all: bool,
+    /// Traits derived by the generated `{Model}Partial` besides `Default`
+    #[darling(default)]
+    partial_derive: darling::util::PathList,
+}
+
+/// Options of the `#[ts_field(...)]` field attribute
+///
+/// Accepts an optional leading index followed by `key = value` pairs, e.g.
+/// `#[ts_field(3)]`, `#[ts_field(name = "type")]`, `#[ts_field(with = "yyyymmdd")]`
+/// or `#[ts_field(3, name = "type")]`.
+#[derive(Debug, Default)]
+struct TsFieldOpts {
+    /// Column index, only used with `#[response(index_fallback)]`
+    index: Option<usize>,
+    /// Column name, defaults to the field name
+    name: Option<String>,
+    /// Converter parsing the cell, a built-in name or the path of a `ts_model::convert::Convert` type
+    with: Option<syn::LitStr>,
+}
+
+impl TsFieldOpts {
+    fn from_attrs(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut opts = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("ts_field")) {
+            attr.parse_args_with(|input: syn::parse::ParseStream| {
+                if input.peek(LitInt) {
+                    opts.index = Some(input.parse::<LitInt>()?.base10_parse()?);
+                    if input.is_empty() {
+                        return Ok(());
+                    }
+                    input.parse::<syn::Token![,]>()?;
+                }
+
+                while !input.is_empty() {
+                    let key: syn::Ident = input.parse()?;
+                    input.parse::<syn::Token![=]>()?;
+                    let value: syn::LitStr = input.parse()?;
+
+                    if key == "name" {
+                        opts.name = Some(value.value());
+                    } else if key == "with" {
+                        opts.with = Some(value);
+                    } else {
+                        return Err(syn::Error::new_spanned(key, "unknown ts_field option"));
+                    }
+
+                    if !input.is_empty() {
+                        input.parse::<syn::Token![,]>()?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(opts)
+    }
+
+    /// The column in `data.fields` the field is read from
+    fn column_name(&self, field_name: &syn::Ident) -> String {
+        use syn::ext::IdentExt;
+
+        self.name
+            .clone()
+            .unwrap_or_else(|| field_name.unraw().to_string())
+    }
+
+    /// The `Convert` type named by `with`, if any
+    fn converter(&self) -> syn::Result<Option<syn::Path>> {
+        let Some(with) = &self.with else {
+            return Ok(None);
+        };
+
+        let builtin = match with.value().as_str() {
+            "yyyymmdd" => Some(quote! { ::ts_model::convert::Yyyymmdd }),
+            "hhmmss" => Some(quote! { ::ts_model::convert::Hhmmss }),
+            "datetime" => Some(quote! { ::ts_model::convert::Datetime }),
+            "shanghai" => Some(quote! { ::ts_model::convert::Shanghai }),
+            _ => None,
+        };
+        match builtin {
+            Some(path) => syn::parse2(path).map(Some),
+            None => with.parse().map(Some),
+        }
+    }
+}
+
+/// The `#[serde(...)]` options of a request field that affect its param
+#[derive(Default)]
+struct SerdeParamOpts {
+    rename: Option<String>,
+    skip: bool,
+    skip_if: Option<syn::ExprPath>,
+    default: bool,
+}
+
+impl SerdeParamOpts {
+    fn from_attrs(attrs: &[syn::Attribute]) -> Self {
+        let mut opts = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    opts.rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    opts.skip = true;
+                } else if meta.path.is_ident("skip_serializing_if") {
+                    let path = meta.value()?.parse::<syn::LitStr>()?;
+                    opts.skip_if = Some(path.parse::<syn::ExprPath>()?);
+                } else if meta.path.is_ident("default") {
+                    opts.default = true;
+                    if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                } else if meta.input.peek(syn::Token![=]) {
+                    // Consume the value of attributes we don't care about
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            });
+        }
+        opts
+    }
+
+    /// The param name sent to Tushare
+    fn param_name(&self, field_name: &syn::Ident) -> String {
+        use syn::ext::IdentExt;
+
+        self.rename
+            .clone()
+            .unwrap_or_else(|| field_name.unraw().to_string())
+    }
+}
+
+/// The doc comment of an item joined into one line
+fn doc_string(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_string()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Derive macro for Tushare API endpoints
 ///
+/// With `resp` set the struct also implements `ts_model::TsApi`, and
+/// `with_fields` takes the response's field enum; the selected columns are
+/// parsed into its partial model by `execute_partial`.
+///
+/// With `tool = "name"` the endpoint is also registered as an MCP tool (see
+/// `ts_model::tools`); the request struct must then derive `Deserialize` and
+/// `JsonSchema`, and its field docs describe the tool arguments.
+///
+/// Every endpoint is listed in `ts_model::registry` with its params and response.
+///
 /// Example usage:
-/// ```rust
+/// ```ignore
 /// #[derive(TsEndpoint)]
 /// #[endpoint(api = "api_name", desc = "description", resp = MyResponseType)]
 /// struct MyRequest {
@@ -80,197 +263,314 @@ pub fn ts_endpoint_derive(input: TokenStream) -> TokenStream {
         }
     };
 
+    // Get API name and description from the endpoint options
+    let api_name = &endpoint_opts.api;
+    let api_desc = &endpoint_opts.desc;
+
     // Generate field serialization for the params object
+    let skip_empty = endpoint_opts.skip_empty;
     let param_fields = fields.iter().map(|field| {
         let field_name = field.ident.as_ref().unwrap();
-        let field_name_str = field_name.to_string();
-
-        // Check for serde rename attribute
-        let mut rename_value = None;
-        for attr in &field.attrs {
-            if attr.path().is_ident("serde") {
-                let _ = attr.parse_nested_meta(|meta| {
-                    if meta.path.is_ident("rename") {
-                        rename_value = Some(meta.value()?.parse::<syn::LitStr>()?.value());
-                    }
-                    Ok(())
-                });
-            }
+        let serde_opts = SerdeParamOpts::from_attrs(&field.attrs);
+        if serde_opts.skip {
+            return quote! {};
         }
+        let param_name = serde_opts.param_name(field_name);
 
-        // Use rename value if present, otherwise use field name
-        let param_name = rename_value.unwrap_or_else(|| field_name_str.clone());
+        // `None` is omitted rather than sent as null, empty strings only with `skip_empty`
+        let keep = if skip_empty {
+            quote! { !value.is_null() && value.as_str() != Some("") }
+        } else {
+            quote! { !value.is_null() }
+        };
+        // Dates are sent as `YYYYMMDD` rather than chrono's ISO format
+        let to_value = if field_type_is_date(&field.ty) {
+            quote! { ::ts_model::convert::DateParam::to_param(&self.#field_name) }
+        } else {
+            quote! {
+                serde_json::to_value(&self.#field_name).map_err(|source| ::ts_model::TsError::Params {
+                    api_name: #api_name.to_string(),
+                    source,
+                })?
+            }
+        };
+        let insert = quote! {
+            let value = #to_value;
+            if #keep {
+                params.insert(#param_name.to_string(), value);
+            }
+        };
 
-        quote! {
-            params.insert(#param_name.to_string(), serde_json::to_value(&self.#field_name)?);
+        match serde_opts.skip_if {
+            Some(skip_if) => quote! {
+                if !#skip_if(&self.#field_name) {
+                    #insert
+                }
+            },
+            None => quote! {
+                {
+                    #insert
+                }
+            },
         }
     });
 
-    // Get API name and description from the endpoint options
-    let api_name = &endpoint_opts.api;
-    let api_desc = &endpoint_opts.desc;
-
     // Check if response type is specified
     let resp_type = endpoint_opts.resp.as_ref().map(|path| quote! { #path });
 
-    // Generate the TsRequesterImpl struct implementation with a unique name
-    let ts_requester_impl = if let Some(resp_type) = resp_type.clone() {
-        quote! {
-            // 定义单独的TsRequester结构体和impl，这个结构体是在当前crate中的
-            pub struct #requester_name {
-                request: #name,
-                fields: Option<Vec<&'static str>>,
-            }
+    // Page size used by the `*_all` methods unless overridden on the requester
+    let page_size = endpoint_opts
+        .page_size
+        .map(|page_size| quote! { #page_size })
+        .unwrap_or_else(|| quote! { ::ts_model::DEFAULT_PAGE_SIZE });
 
-            impl #requester_name {
-                pub fn new(request: #name, fields: Option<Vec<&'static str>>) -> Self {
-                    Self { request, fields }
-                }
+    // Per-endpoint settings handed to the client on every call
+    let rate_limit = match endpoint_opts.rate_limit {
+        Some(rate_limit) => quote! { Some(#rate_limit) },
+        None => quote! { None },
+    };
+    let retry = match endpoint_opts.retry {
+        Some(retry) => quote! { Some(#retry) },
+        None => quote! { None },
+    };
 
-                pub fn with_fields(mut self, fields: Vec<&'static str>) -> Self {
-                    self.fields = Some(fields);
-                    self
-                }
+    // Shared by both requester flavours: row dicts keyed by the returned `fields`
+    let execute_as_dicts = quote! {
+        pub async fn execute_as_dicts(self) -> Result<Vec<std::collections::HashMap<String, serde_json::Value>>, ::ts_model::TsError> {
+            let client = ::ts_model::TsClient::shared()?;
+            self.execute_as_dicts_with(&client).await
+        }
 
-                pub async fn execute(self) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-                    self.request.__execute_request(self.fields).await
-                }
+        pub async fn execute_as_dicts_with(self, client: &::ts_model::TsClient) -> Result<Vec<std::collections::HashMap<String, serde_json::Value>>, ::ts_model::TsError> {
+            // 直接使用__execute_request而不是execute，以便保留字段信息
+            let json = self.request.__execute_request(client, self.fields).await?;
 
-                pub async fn execute_typed(self) -> Result<Vec<#resp_type>, Box<dyn std::error::Error>> {
-                    // If fields are not provided, extract field names from the response struct
-                    let fields_to_use = if self.fields.is_none() {
-                        // Get field names from the response struct by reflection
-                        let field_names = <#resp_type>::get_field_names();
-                        Some(field_names)
-                    } else {
-                        self.fields
-                    };
+            let rows = ::ts_model::row::rows_as_dicts(#api_name, &json)?;
+            Ok(rows.into_iter().map(|row| row.into_iter().collect()).collect())
+        }
+    };
 
-                    // Execute with the fields (either provided or derived)
-                    let json = self.request.__execute_request(fields_to_use).await?;
-                    let res = <#resp_type>::from_json(&json);
-                    res
-                }
+    // Typed execution is only available when a response type is specified
+    let execute_typed = resp_type.as_ref().map(|resp_type| {
+        quote! {
+            pub async fn execute_typed(self) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_typed_with(&client).await
+            }
 
-                pub async fn execute_as_dicts(self) -> Result<Vec<std::collections::HashMap<String, serde_json::Value>>, Box<dyn std::error::Error>> {
-                    use serde_json::Value;
-                    use std::collections::HashMap;
+            pub async fn execute_typed_with(self, client: &::ts_model::TsClient) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                // If fields are not provided, extract field names from the response struct
+                let fields_to_use = if self.fields.is_none() {
+                    // Get field names from the response struct by reflection
+                    let field_names = <#resp_type as ::ts_model::TsRow>::field_names();
+                    Some(field_names)
+                } else {
+                    self.fields
+                };
 
-                    // 直接使用__execute_request而不是execute，以便保留字段信息
-                    let json = self.request.__execute_request(self.fields).await?;
+                // Execute with the fields (either provided or derived), decoding the rows in one pass
+                client
+                    .call_typed(#api_name, self.request.__params()?, fields_to_use.as_deref(), #name::__config())
+                    .await
+            }
 
-                    // Extract fields and items
-                    let data = json.get("data")
-                        .ok_or("Missing 'data' field in response")?;
+            pub async fn execute_typed_all(self) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_typed_all_with(&client).await
+            }
 
-                    let fields = data.get("fields")
-                        .ok_or("Missing 'fields' field in data")?
-                        .as_array()
-                        .ok_or("'fields' is not an array")?;
+            pub async fn execute_typed_all_with(self, client: &::ts_model::TsClient) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                let fields_to_use = match self.fields {
+                    Some(fields) => fields,
+                    None => <#resp_type as ::ts_model::TsRow>::field_names(),
+                };
 
-                    let items = data.get("items")
-                        .ok_or("Missing 'items' field in data")?
-                        .as_array()
-                        .ok_or("'items' is not an array")?;
+                client
+                    .call_all_typed(#api_name, self.request.__params()?, Some(&fields_to_use), self.pagination, #name::__config())
+                    .await
+            }
 
-                    // Convert to Vec<HashMap<String, Value>>
-                    let mut result = Vec::with_capacity(items.len());
+            /// Execute with the selected fields only, parsed into the partial model
+            pub async fn execute_partial(self) -> Result<Vec<<#resp_type as ::ts_model::TsRow>::Partial>, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_partial_with(&client).await
+            }
 
-                    for item_value in items {
-                        let item = item_value.as_array()
-                            .ok_or("Item is not an array")?;
+            pub async fn execute_partial_with(self, client: &::ts_model::TsClient) -> Result<Vec<<#resp_type as ::ts_model::TsRow>::Partial>, ::ts_model::TsError> {
+                let fields_to_use = self.fields.unwrap_or_else(<#resp_type as ::ts_model::TsRow>::field_names);
 
-                        let mut map = HashMap::new();
+                client
+                    .call_typed(#api_name, self.request.__params()?, Some(&fields_to_use), #name::__config())
+                    .await
+            }
 
-                        // Map fields to values
-                        for (i, field) in fields.iter().enumerate() {
-                            if i < item.len() {
-                                let field_name = field.as_str()
-                                    .ok_or("Field name is not a string")?
-                                    .to_string();
+            /// Fetch every page of the selected fields, parsed into the partial model
+            pub async fn execute_partial_all(self) -> Result<Vec<<#resp_type as ::ts_model::TsRow>::Partial>, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_partial_all_with(&client).await
+            }
 
-                                map.insert(field_name, item[i].clone());
-                            }
-                        }
+            pub async fn execute_partial_all_with(self, client: &::ts_model::TsClient) -> Result<Vec<<#resp_type as ::ts_model::TsRow>::Partial>, ::ts_model::TsError> {
+                let fields_to_use = self.fields.unwrap_or_else(<#resp_type as ::ts_model::TsRow>::field_names);
 
-                        result.push(map);
-                    }
+                client
+                    .call_all_typed(#api_name, self.request.__params()?, Some(&fields_to_use), self.pagination, #name::__config())
+                    .await
+            }
 
-                    Ok(result)
+            /// Stream typed rows page by page instead of collecting them
+            pub fn execute_stream(self) -> ::ts_model::TsStream<#resp_type> {
+                match ::ts_model::TsClient::shared() {
+                    Ok(client) => self.execute_stream_with(&client),
+                    Err(e) => ::ts_model::stream::error(e),
+                }
+            }
+
+            pub fn execute_stream_with(self, client: &::ts_model::TsClient) -> ::ts_model::TsStream<#resp_type> {
+                let fields_to_use = self.fields.unwrap_or_else(<#resp_type as ::ts_model::TsRow>::field_names);
+                let fields_to_use = fields_to_use.into_iter().map(String::from).collect();
+
+                match self.request.__params() {
+                    Ok(params) => client.typed_rows(#api_name, params, Some(fields_to_use), self.pagination, #name::__config()),
+                    Err(e) => ::ts_model::stream::error(e),
                 }
             }
         }
-    } else {
-        quote! {
-            // 定义单独的TsRequester结构体和impl，这个结构体是在当前crate中的
-            pub struct #requester_name {
-                request: #name,
-                fields: Option<Vec<&'static str>>,
+    });
+
+    // Typed endpoints select fields through the generated field enum
+    let requester_with_fields = match &resp_type {
+        Some(resp_type) => quote! {
+            /// Select the columns to request, see `execute_partial` for the typed rows
+            pub fn with_fields(mut self, fields: impl IntoIterator<Item = <#resp_type as ::ts_model::TsRow>::Field>) -> Self {
+                self.fields = Some(fields.into_iter().map(::ts_model::TsField::column_name).collect());
+                self
+            }
+        },
+        None => quote! {
+            pub fn with_fields(mut self, fields: Vec<&'static str>) -> Self {
+                self.fields = Some(fields);
+                self
+            }
+        },
+    };
+    let struct_with_fields = match &resp_type {
+        Some(resp_type) => quote! {
+            /// Start chain with fields
+            pub fn with_fields(self, fields: impl IntoIterator<Item = <#resp_type as ::ts_model::TsRow>::Field>) -> #requester_name {
+                #requester_name::new(self, None).with_fields(fields)
+            }
+        },
+        None => quote! {
+            /// Start chain with fields
+            pub fn with_fields(self, fields: Vec<&'static str>) -> #requester_name {
+                #requester_name::new(self, Some(fields))
             }
+        },
+    };
 
-            impl #requester_name {
-                pub fn new(request: #name, fields: Option<Vec<&'static str>>) -> Self {
-                    Self { request, fields }
-                }
+    // Generate the TsRequesterImpl struct implementation with a unique name
+    let ts_requester_impl = quote! {
+        // 定义单独的TsRequester结构体和impl，这个结构体是在当前crate中的
+        pub struct #requester_name {
+            request: #name,
+            fields: Option<Vec<&'static str>>,
+            pagination: ::ts_model::Pagination,
+        }
 
-                pub fn with_fields(mut self, fields: Vec<&'static str>) -> Self {
-                    self.fields = Some(fields);
-                    self
+        impl #requester_name {
+            pub fn new(request: #name, fields: Option<Vec<&'static str>>) -> Self {
+                Self {
+                    request,
+                    fields,
+                    pagination: ::ts_model::Pagination::new(#page_size),
                 }
+            }
 
-                pub async fn execute(self) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-                    self.request.__execute_request(self.fields).await
-                }
+            #requester_with_fields
+
+            /// Select the columns by name
+            pub fn with_field_names(mut self, fields: Vec<&'static str>) -> Self {
+                self.fields = Some(fields);
+                self
+            }
 
-                pub async fn execute_as_dicts(self) -> Result<Vec<std::collections::HashMap<String, serde_json::Value>>, Box<dyn std::error::Error>> {
-                    use serde_json::Value;
-                    use std::collections::HashMap;
+            /// Rows requested per call by the `*_all` methods
+            pub fn page_size(mut self, page_size: usize) -> Self {
+                self.pagination = self.pagination.page_size(page_size);
+                self
+            }
 
-                    // 直接使用__execute_request而不是execute，以便保留字段信息
-                    let json = self.request.__execute_request(self.fields).await?;
+            /// Fail instead of fetching more than `max_rows` rows in the `*_all` methods
+            pub fn max_rows(mut self, max_rows: usize) -> Self {
+                self.pagination = self.pagination.max_rows(max_rows);
+                self
+            }
 
-                    // Extract fields and items
-                    let data = json.get("data")
-                        .ok_or("Missing 'data' field in response")?;
+            /// Fetch every page and concatenate the `items` into one response body
+            pub async fn execute_all(self) -> Result<serde_json::Value, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_all_with(&client).await
+            }
 
-                    let fields = data.get("fields")
-                        .ok_or("Missing 'fields' field in data")?
-                        .as_array()
-                        .ok_or("'fields' is not an array")?;
+            pub async fn execute_all_with(self, client: &::ts_model::TsClient) -> Result<serde_json::Value, ::ts_model::TsError> {
+                client
+                    .call_all(#api_name, self.request.__params()?, self.fields.as_deref(), self.pagination, #name::__config())
+                    .await
+            }
 
-                    let items = data.get("items")
-                        .ok_or("Missing 'items' field in data")?
-                        .as_array()
-                        .ok_or("'items' is not an array")?;
+            pub async fn execute(self) -> Result<serde_json::Value, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.execute_with(&client).await
+            }
 
-                    // Convert to Vec<HashMap<String, Value>>
-                    let mut result = Vec::with_capacity(items.len());
+            pub async fn execute_with(self, client: &::ts_model::TsClient) -> Result<serde_json::Value, ::ts_model::TsError> {
+                self.request.__execute_request(client, self.fields).await
+            }
 
-                    for item_value in items {
-                        let item = item_value.as_array()
-                            .ok_or("Item is not an array")?;
+            #execute_typed
 
-                        let mut map = HashMap::new();
+            #execute_as_dicts
+        }
+    };
 
-                        // Map fields to values
-                        for (i, field) in fields.iter().enumerate() {
-                            if i < item.len() {
-                                let field_name = field.as_str()
-                                    .ok_or("Field name is not a string")?
-                                    .to_string();
+    // Typed shortcuts on the request struct itself
+    let struct_execute_typed = resp_type.as_ref().map(|resp_type| {
+        quote! {
+            /// Execute with typed response, automatically deriving fields from response struct
+            pub async fn execute_typed(self) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                // Create requester and call its execute_typed method
+                let requester = #requester_name::new(self, None);
+                requester.execute_typed().await
+            }
 
-                                map.insert(field_name, item[i].clone());
-                            }
-                        }
+            /// Execute with typed response against the given client
+            pub async fn execute_typed_with(self, client: &::ts_model::TsClient) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                let requester = #requester_name::new(self, None);
+                requester.execute_typed_with(client).await
+            }
 
-                        result.push(map);
-                    }
+            /// Execute with typed response, fetching every page
+            pub async fn execute_typed_all(self) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                #requester_name::new(self, None).execute_typed_all().await
+            }
 
-                    Ok(result)
-                }
+            /// Execute with typed response against the given client, fetching every page
+            pub async fn execute_typed_all_with(self, client: &::ts_model::TsClient) -> Result<Vec<#resp_type>, ::ts_model::TsError> {
+                #requester_name::new(self, None).execute_typed_all_with(client).await
+            }
+
+            /// Stream typed rows page by page using the shared client
+            pub fn execute_stream(self) -> ::ts_model::TsStream<#resp_type> {
+                #requester_name::new(self, None).execute_stream()
+            }
+
+            /// Stream typed rows page by page against the given client
+            pub fn execute_stream_with(self, client: &::ts_model::TsClient) -> ::ts_model::TsStream<#resp_type> {
+                #requester_name::new(self, None).execute_stream_with(client)
             }
         }
-    };
+    });
 
     // Generate impl for the struct
     let impl_struct = quote! {
@@ -285,69 +585,187 @@ pub fn ts_endpoint_derive(input: TokenStream) -> TokenStream {
                 #api_desc
             }
 
-            /// Start chain with fields
-            pub fn with_fields(self, fields: Vec<&'static str>) -> #requester_name {
+            #struct_with_fields
+
+            /// Start chain with fields selected by column name
+            pub fn with_field_names(self, fields: Vec<&'static str>) -> #requester_name {
                 #requester_name::new(self, Some(fields))
             }
 
-            /// Execute without fields
-            pub async fn execute(self) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-                self.__execute_request(None).await
+            /// Execute without fields using the shared client
+            pub async fn execute(self) -> Result<serde_json::Value, ::ts_model::TsError> {
+                let client = ::ts_model::TsClient::shared()?;
+                self.__execute_request(&client, None).await
             }
 
-            /// Execute with typed response, automatically deriving fields from response struct
-            pub async fn execute_typed(self) -> Result<Vec<#resp_type>, Box<dyn std::error::Error>> {
-                // Create requester and call its execute_typed method
-                let requester = #requester_name::new(self, None);
-                requester.execute_typed().await
+            /// Execute without fields against the given client
+            pub async fn execute_with(self, client: &::ts_model::TsClient) -> Result<serde_json::Value, ::ts_model::TsError> {
+                self.__execute_request(client, None).await
             }
 
-            // Inner method used by TsRequester
-            #[doc(hidden)]
-            pub(crate) async fn __execute_request(&self, fields: Option<Vec<&str>>) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-                use serde_json::{json, Map, Value};
-                use reqwest::Client;
-                use dotenvy::dotenv;
-                use std::env;
+            #struct_execute_typed
 
-                // Load environment variables
-                dotenv().ok();
+            /// Fetch every page without fields using the shared client
+            pub async fn execute_all(self) -> Result<serde_json::Value, ::ts_model::TsError> {
+                #requester_name::new(self, None).execute_all().await
+            }
 
-                // Get token from environment
-                let token = env::var("TUSHARE_TOKEN")
-                    .map_err(|_| "TUSHARE_TOKEN environment variable not set")?;
+            /// Fetch every page without fields against the given client
+            pub async fn execute_all_with(self, client: &::ts_model::TsClient) -> Result<serde_json::Value, ::ts_model::TsError> {
+                #requester_name::new(self, None).execute_all_with(client).await
+            }
 
+            // Inner method used by TsRequester
+            #[doc(hidden)]
+            pub(crate) fn __config() -> ::ts_model::EndpointConfig {
+                ::ts_model::EndpointConfig {
+                    rate_limit: #rate_limit,
+                    retry: #retry,
+                }
+            }
+
+            // Inner method used by TsRequester
+            #[doc(hidden)]
+            pub(crate) fn __params(&self) -> Result<serde_json::Map<String, serde_json::Value>, ::ts_model::TsError> {
                 // Build params object
-                let mut params = Map::new();
+                let mut params = serde_json::Map::new();
                 #(#param_fields)*
+                Ok(params)
+            }
+
+            // Inner method used by TsRequester
+            #[doc(hidden)]
+            pub(crate) async fn __execute_request(&self, client: &::ts_model::TsClient, fields: Option<Vec<&str>>) -> Result<serde_json::Value, ::ts_model::TsError> {
+                client.call_as(#api_name, self.__params()?, fields.as_deref(), Self::__config()).await
+            }
+        }
+    };
+
+    // Generic access to the endpoint through `TsApi`, for typed endpoints only
+    let api_impl = resp_type.as_ref().map(|resp_type| {
+        quote! {
+            impl ::ts_model::TsApi for #name {
+                const API_NAME: &'static str = #api_name;
+                const DESCRIPTION: &'static str = #api_desc;
+                const PAGE_SIZE: usize = #page_size;
 
-                // Create request body
-                let mut request_body = Map::new();
-                request_body.insert("api_name".to_string(), Value::String(#api_name.to_string()));
-                request_body.insert("token".to_string(), Value::String(token));
-                request_body.insert("params".to_string(), Value::Object(params));
+                type Response = #resp_type;
 
-                // Add fields if provided
-                if let Some(field_list) = fields {
-                    request_body.insert("fields".to_string(),
-                        Value::String(field_list.join(",")));
+                fn params(&self) -> Result<serde_json::Map<String, serde_json::Value>, ::ts_model::TsError> {
+                    self.__params()
                 }
 
-                // Send request
-                let client = Client::new();
-                let response = client
-                    .post("http://api.tushare.pro/")
-                    .header("Content-Type", "application/json")
-                    .body(serde_json::to_string(&Value::Object(request_body))?)
-                    .send()
-                    .await?;
-
-                if !response.status().is_success() {
-                    return Err(format!("Request failed with status: {}", response.status()).into());
+                fn config() -> ::ts_model::EndpointConfig {
+                    Self::__config()
                 }
+            }
+        }
+    });
+
+    // MCP tool registered for `#[endpoint(tool = "...")]`
+    let tool_impl = match (&endpoint_opts.tool, &resp_type) {
+        (None, _) => quote! {},
+        (Some(_), None) => {
+            return syn::Error::new_spanned(
+                input.ident.clone(),
+                "#[endpoint(tool = ...)] requires a `resp` type",
+            )
+            .to_compile_error()
+            .into()
+        }
+        (Some(tool_name), Some(resp_type)) => {
+            let (arg_names, arg_docs): (Vec<_>, Vec<_>) = fields
+                .iter()
+                .filter_map(|field| {
+                    let serde_opts = SerdeParamOpts::from_attrs(&field.attrs);
+                    (!serde_opts.skip).then(|| {
+                        (
+                            serde_opts.param_name(field.ident.as_ref().unwrap()),
+                            doc_string(&field.attrs),
+                        )
+                    })
+                })
+                .unzip();
+
+            quote! {
+                impl #name {
+                    // Tool description, built once from the params and response docs
+                    #[doc(hidden)]
+                    pub fn __tool_description() -> &'static str {
+                        static DESCRIPTION: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+                        DESCRIPTION.get_or_init(|| {
+                            ::ts_model::tool::tool_description(
+                                #api_desc,
+                                &[#((#arg_names, #arg_docs)),*],
+                                &#resp_type::field_docs(),
+                            )
+                        })
+                    }
 
-                let json = response.json::<Value>().await?;
-                Ok(json)
+                    // JSON schema of the tool arguments
+                    #[doc(hidden)]
+                    pub fn __tool_input_schema() -> serde_json::Value {
+                        let schema = ::ts_model::tool::schemars::r#gen::SchemaGenerator::default()
+                            .into_root_schema_for::<Self>();
+                        serde_json::to_value(schema).unwrap_or_default()
+                    }
+
+                    // Deserialize the arguments, execute and return the rows as JSON
+                    #[doc(hidden)]
+                    pub fn __tool_call(client: ::ts_model::TsClient, arguments: serde_json::Value) -> ::ts_model::tool::ToolFuture {
+                        ::ts_model::tool::call::<Self>(#tool_name, client, arguments)
+                    }
+                }
+
+                ::ts_model::inventory::submit! {
+                    ::ts_model::TsTool {
+                        name: #tool_name,
+                        description: #name::__tool_description,
+                        input_schema: #name::__tool_input_schema,
+                        call: #name::__tool_call,
+                    }
+                }
+            }
+        }
+    };
+
+    // Registry entry listing the params and the response model
+    let param_infos = fields.iter().filter_map(|field| {
+        let serde_opts = SerdeParamOpts::from_attrs(&field.attrs);
+        if serde_opts.skip {
+            return None;
+        }
+        let param_name = serde_opts.param_name(field.ident.as_ref().unwrap());
+        let param_type = type_name(&field.ty);
+        let required = !field_type_is_option(&field.ty) && !serde_opts.default;
+        let doc = doc_string(&field.attrs);
+        Some(quote! {
+            ::ts_model::registry::ParamInfo {
+                name: #param_name,
+                ty: #param_type,
+                required: #required,
+                doc: #doc,
+            }
+        })
+    });
+    let response_info = match &resp_type {
+        Some(resp_type) => quote! { Some(&<#resp_type>::RESPONSE_INFO) },
+        None => quote! { None },
+    };
+    let tool_name = match &endpoint_opts.tool {
+        Some(tool_name) => quote! { Some(#tool_name) },
+        None => quote! { None },
+    };
+    let struct_name = name.to_string();
+    let registry_entry = quote! {
+        ::ts_model::inventory::submit! {
+            ::ts_model::registry::EndpointInfo {
+                name: #struct_name,
+                api_name: #api_name,
+                description: #api_desc,
+                params: &[#(#param_infos),*],
+                response: #response_info,
+                tool: #tool_name,
             }
         }
     };
@@ -356,6 +774,9 @@ pub fn ts_endpoint_derive(input: TokenStream) -> TokenStream {
     let output = quote! {
         #impl_struct
         #ts_requester_impl
+        #api_impl
+        #tool_impl
+        #registry_entry
     };
 
     output.into()
@@ -364,23 +785,50 @@ pub fn ts_endpoint_derive(input: TokenStream) -> TokenStream {
 /// Derive macro for Tushare API response models
 ///
 /// This macro will create structs that represent the response data from a Tushare API call.
-/// It automatically maps the fields to the data items in the response.
+/// It automatically maps the fields to the data items in the response by looking up each
+/// field name in `data.fields`. With `#[response(index_fallback)]` the `#[ts_field(index)]`
+/// is used for columns that are not listed. `#[response(default_all)]` applies
+/// `#[serde(default)]` to every field.
+///
+/// `#[ts_field(with = "yyyymmdd")]` parses a cell through a converter from
+/// `ts_model::convert` (`yyyymmdd`, `hhmmss`, `datetime`, `shanghai`) or any
+/// type implementing `ts_model::convert::Convert`.
+///
+/// Implements `ts_model::TsRow` for the parsing and `TryFrom<&serde_json::Value>`
+/// for a single row. Also generates `MyResponseDataField`, an enum with one
+/// variant per field for typed field selection, and unless `default_all` is set
+/// `MyResponseDataPartial`, the same struct with every field optional, returned
+/// when only some fields are requested. The partial model only derives `Default`
+/// unless more is listed in `#[response(partial_derive(Debug, Clone, Serialize))]`,
+/// as a derive cannot see what else the model derives; with `Serialize` the unset
+/// fields are left out. The model and its columns are listed in `ts_model::registry`.
 ///
 /// Example usage:
-/// ```rust
+/// ```ignore
 /// #[derive(TsResponse)]
 /// #[response(api = "api_name")]
 /// struct MyResponseData {
-///     #[ts_field(0)]
 ///     field_one: String,
-///     #[ts_field(1)]
-///     field_two: i64,
+///     #[ts_field(name = "type")]
+///     field_two: String,
+///     field_three: Option<i64>,
 ///     // ...
 /// }
 /// ```
 #[proc_macro_derive(TsResponse, attributes(response, ts_field))]
 pub fn ts_response_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand_response(&input, None).into()
+}
+
+/// Expand `#[derive(TsResponse)]`
+///
+/// `partial_of` is set when expanding the generated `{Model}Partial` projection,
+/// which shares the field enum of its model and is its own partial type.
+fn expand_response(
+    input: &DeriveInput,
+    partial_of: Option<&syn::Ident>,
+) -> proc_macro2::TokenStream {
     let name = &input.ident;
 
     // Parse response options
@@ -398,9 +846,8 @@ pub fn ts_response_derive(input: TokenStream) -> TokenStream {
                 "Missing #[response(...)] attribute",
             )
             .to_compile_error()
-            .into()
         }
-        Err(e) => return TokenStream::from(e.write_errors()),
+        Err(e) => return e.write_errors(),
     };
 
     // Extract fields for response data
@@ -413,42 +860,49 @@ pub fn ts_response_derive(input: TokenStream) -> TokenStream {
                     "TsResponse only supports structs with named fields",
                 )
                 .to_compile_error()
-                .into()
             }
         },
         _ => {
             return syn::Error::new_spanned(input.ident.clone(), "TsResponse only supports structs")
                 .to_compile_error()
-                .into()
         }
     };
 
-    // Generate field parsing for the response items
-    let field_parsers = fields.iter().map(|field| {
+    // Get API name
+    let api_name = &response_opts.api;
+
+    // Generate column lookups (once per response) and field parsing (once per row)
+    let mut column_lookups = Vec::new();
+    let mut field_parsers = Vec::new();
+
+    let mut columns = Vec::new();
+    let mut builder_slots = Vec::new();
+    let mut cell_arms = Vec::new();
+    let mut field_builders = Vec::new();
+
+    for (position, field) in fields.iter().enumerate() {
         let field_name = field.ident.as_ref().unwrap();
         let field_type = &field.ty;
+        let column = format_ident!("__col_{}", field_name);
+        columns.push(column.clone());
 
-        // Extract index from ts_field attribute
-        let mut field_index = None;
+        // Extract index and column name from ts_field attribute
+        let ts_field = match TsFieldOpts::from_attrs(&field.attrs) {
+            Ok(opts) => opts,
+            Err(e) => return e.to_compile_error(),
+        };
+        let column_name = ts_field.column_name(field_name);
+        let converter = match ts_field.converter() {
+            Ok(converter) => converter,
+            Err(e) => return e.to_compile_error(),
+        };
         // Check for #[serde(default)] attribute
-        let mut has_serde_default = false;
+        let mut has_serde_default = response_opts.default_all;
 
         for attr in &field.attrs {
-            if attr.path().is_ident("ts_field") {
-                match attr.meta.require_list() {
-                    Ok(nested) => {
-                        // Parse the first token in the list as a literal integer
-                        let lit: LitInt = match syn::parse2(nested.tokens.clone()) {
-                            Ok(lit) => lit,
-                            Err(e) => return e.to_compile_error(),
-                        };
-                        field_index = Some(lit.base10_parse::<usize>().unwrap());
-                    }
-                    Err(e) => return e.to_compile_error(),
-                }
-            } else if attr.path().is_ident("serde") {
-                 // Use parse_nested_meta for a more robust check
-                 let _ = attr.parse_nested_meta(|meta| {
+            if attr.path().is_ident("serde") {
+                // Use parse_nested_meta for a more robust check
+                let _ = attr.parse_nested_meta(|meta| {
                     if meta.path.is_ident("default") {
                         has_serde_default = true;
                     }
@@ -460,61 +914,165 @@ pub fn ts_response_derive(input: TokenStream) -> TokenStream {
             }
         }
 
-        let index = match field_index {
-            Some(idx) => idx,
-            None => {
-                return syn::Error::new_spanned(field_name, "Missing #[ts_field(index)] attribute")
-                    .to_compile_error()
+        let is_option = field_type_is_option(field_type);
+        let required = !is_option && !has_serde_default;
+
+        // Resolve the column by name, using the declared index only when asked to
+        let fallback = match ts_field.index {
+            Some(index) if response_opts.index_fallback => quote! { Some(#index) },
+            _ => quote! { None },
+        };
+        let missing_column = if required {
+            quote! {
+                if #column.is_none() {
+                    return Err(::ts_model::TsError::MissingColumn {
+                        api_name: #api_name.to_string(),
+                        field: #column_name,
+                    });
+                }
             }
+        } else {
+            quote! {}
         };
+        column_lookups.push(quote! {
+            let #column: Option<usize> = columns
+                .get(#column_name)
+                .copied()
+                .or(#fallback);
+            #missing_column
+        });
+
+        field_parsers.push(quote! {
+            let #column: Option<usize> = columns.get(#position).copied().flatten();
+        });
+
+        // Deserialize a cell as the field type, or through the converter
+        let deserialize = |deserializer: proc_macro2::TokenStream| match &converter {
+            Some(converter) => {
+                quote! { ::ts_model::convert::deserialize::<#converter, _>(#deserializer) }
+            }
+            None => quote! { serde::Deserialize::deserialize(#deserializer) },
+        };
+        // The same for a cell that may be null, yielding an Option
+        let deserialize_option = |deserializer: proc_macro2::TokenStream| match &converter {
+            Some(converter) => {
+                quote! { ::ts_model::convert::deserialize_option::<#converter, _>(#deserializer) }
+            }
+            None => quote! { <Option<_> as serde::Deserialize>::deserialize(#deserializer) },
+        };
+        let deserialize_val = deserialize(quote! { val });
 
-        let from_value = if field_type_is_option(field_type) {
+        let from_value = if is_option {
             // Logic for Option<T>
+            let deserialize_some = deserialize_option(quote! { val });
             quote! {
-                let #field_name = if item.len() > #index {
-                    let val = &item[#index];
-                    if val.is_null() {
-                        None
-                    } else {
-                        Some(serde_json::from_value(val.clone())?)
-                    }
-                } else {
-                    None // Treat missing index as None for Option types
+                let #field_name = match #column.and_then(|index| item.get(index).map(|val| (index, val))) {
+                    Some((index, val)) if !val.is_null() => #deserialize_some
+                        .map_err(|e| ::ts_model::TsError::parse(#api_name, #column_name, index, e))?,
+                    // Treat missing columns and nulls as None for Option types
+                    _ => None,
                 };
             }
         } else if has_serde_default {
             // Logic for non-Option<T> with #[serde(default)]
-             quote! {
-                let #field_name:#field_type = if item.len() > #index {
-                    let val = &item[#index];
-                    if val.is_null() {
-                        Default::default() // Use default if null
-                    } else {
-                        // Using unwrap_or_default() on the Result is cleaner
-                        serde_json::from_value(val.clone()).unwrap_or_default()
-                    }
-                } else {
-                    Default::default() // Use default if index out of bounds
+            quote! {
+                let #field_name: #field_type = match #column.and_then(|index| item.get(index)) {
+                    // Using unwrap_or_default() on the Result is cleaner
+                    Some(val) if !val.is_null() => #deserialize_val.unwrap_or_default(),
+                    // Use default if the column is missing or the value is null
+                    _ => Default::default(),
                 };
             }
         } else {
             // Logic for non-Option<T> *without* #[serde(default)]
             quote! {
-                let #field_name = if item.len() > #index {
-                    let val = &item[#index];
-                     // Error on null for non-optional, non-default fields
+                let #field_name = {
+                    // The column was checked to exist before parsing the rows
+                    let index = #column.unwrap_or_default();
+                    let val = item.get(index).ok_or_else(|| {
+                        ::ts_model::TsError::parse(#api_name, #column_name, index, "index out of bounds for required field")
+                    })?;
+                    // Error on null for non-optional, non-default fields
                     if val.is_null() {
-                         return Err(format!("Field '{}' at index {} is null, but type is not Option and #[serde(default)] is not specified", stringify!(#field_name), #index).into());
+                        return Err(::ts_model::TsError::parse(
+                            #api_name,
+                            #column_name,
+                            index,
+                            "value is null, but type is not Option and #[serde(default)] is not specified",
+                        ));
                     }
-                    serde_json::from_value(val.clone())?
-                } else {
-                    return Err(format!("Field index {} out of bounds for required field '{}'", #index, stringify!(#field_name)).into());
+                    #deserialize_val
+                        .map_err(|e| ::ts_model::TsError::parse(#api_name, #column_name, index, e))?
                 };
             }
         };
 
-        quote! { #from_value }
-    });
+        field_parsers.push(from_value);
+
+        // Single-pass path: a builder slot per field, filled cell by cell
+        let deserialize_cell = deserialize_option(quote! { cell });
+        let (slot_type, deserialize_cell, build_field) = if is_option {
+            (
+                quote! { #field_type },
+                quote! { #deserialize_cell? },
+                quote! { let #field_name = __builder.#field_name.flatten(); },
+            )
+        } else if has_serde_default {
+            // Mismatched cells fall back to the default too, as in `from_row`
+            let deserialize_default = match &converter {
+                Some(converter) => {
+                    quote! { ::ts_model::convert::deserialize_or_default::<#converter, _>(cell)? }
+                }
+                None => quote! { ::ts_model::de::deserialize_or_default(cell)? },
+            };
+            (
+                quote! { #field_type },
+                deserialize_default,
+                quote! { let #field_name = __builder.#field_name.unwrap_or_default(); },
+            )
+        } else {
+            (
+                quote! { Option<#field_type> },
+                quote! { #deserialize_cell? },
+                quote! {
+                    let #field_name = {
+                        let index = columns.get(#position).copied().flatten().unwrap_or_default();
+                        match __builder.#field_name {
+                            Some(Some(val)) => val,
+                            Some(None) => {
+                                return Err(::ts_model::TsError::parse(
+                                    #api_name,
+                                    #column_name,
+                                    index,
+                                    "value is null, but type is not Option and #[serde(default)] is not specified",
+                                ))
+                            }
+                            None => {
+                                return Err(::ts_model::TsError::parse(
+                                    #api_name,
+                                    #column_name,
+                                    index,
+                                    "index out of bounds for required field",
+                                ))
+                            }
+                        }
+                    };
+                },
+            )
+        };
+        builder_slots.push(quote! { #field_name: Option<#slot_type> });
+        cell_arms.push(quote! { #position => __builder.#field_name = Some(#deserialize_cell) });
+        field_builders.push(build_field);
+    }
+
+    // Without `data.fields` the columns can only be resolved by index
+    let missing_fields = if response_opts.index_fallback {
+        quote! { &[] }
+    } else {
+        quote! {
+            return Err(::ts_model::TsError::response(#api_name, "Missing 'fields' field in data"))
+        }
+    };
 
     // 生成字段名称列表（用于构造和获取字段名）
     let field_names: Vec<_> = fields
@@ -522,6 +1080,25 @@ pub fn ts_response_derive(input: TokenStream) -> TokenStream {
         .map(|field| field.ident.as_ref().unwrap().clone())
         .collect();
 
+    // 请求时使用的列名
+    let column_names: Vec<_> = match fields
+        .iter()
+        .map(|field| {
+            TsFieldOpts::from_attrs(&field.attrs)
+                .map(|opts| opts.column_name(field.ident.as_ref().unwrap()))
+        })
+        .collect::<syn::Result<_>>()
+    {
+        Ok(names) => names,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    // 字段文档(用于生成 MCP 工具描述)
+    let column_docs: Vec<_> = fields
+        .iter()
+        .map(|field| doc_string(&field.attrs))
+        .collect();
+
     // 生成用于构造结构体的字段列表
     let struct_field_tokens = {
         let field_idents = &field_names;
@@ -530,65 +1107,297 @@ pub fn ts_response_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    // Get API name
-    let api_name = &response_opts.api;
+    // Row builder used by the single-pass deserializer
+    let vis = &input.vis;
+    let builder_name = format_ident!("__{}RowBuilder", name);
 
-    // Generate implementation for parsing response
-    let output = quote! {
-        impl #name {
-            /// Parse a list of items from Tushare API response
-            pub fn from_json(json: &serde_json::Value) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
-                use serde_json::Value;
+    // Registry entry of the model; partial projections are not listed
+    let registry_entry = if partial_of.is_none() {
+        let struct_name = name.to_string();
+        let column_types: Vec<_> = fields.iter().map(|field| type_name(&field.ty)).collect();
+        quote! {
+            impl #name {
+                /// The model and its columns as listed in `ts_model::registry`
+                pub const RESPONSE_INFO: ::ts_model::registry::ResponseInfo =
+                    ::ts_model::registry::ResponseInfo {
+                        name: #struct_name,
+                        api_name: #api_name,
+                        fields: &[
+                            #(::ts_model::registry::FieldInfo {
+                                name: #column_names,
+                                ty: #column_types,
+                                doc: #column_docs,
+                            }),*
+                        ],
+                    };
+            }
 
-                // Extract data from response
-                let data = json.get("data")
-                    .ok_or_else(|| "Missing 'data' field in response")?;
+            ::ts_model::inventory::submit! {
+                #name::RESPONSE_INFO
+            }
+        }
+    } else {
+        quote! {}
+    };
 
-                let items = data.get("items")
-                    .ok_or_else(|| "Missing 'items' field in data")?
-                    .as_array()
-                    .ok_or_else(|| "'items' is not an array")?;
+    // Typed field selector, shared by the model and its partial projection
+    let field_enum = format_ident!("{}Field", partial_of.unwrap_or(name));
+    let field_enum_def = if partial_of.is_none() {
+        let enum_doc = format!(
+            "A column of [`{}`], used to select fields with `with_fields`",
+            name
+        );
+        let variants: Vec<_> = field_names.iter().map(variant_name).collect();
+        let variant_docs: Vec<_> = column_names
+            .iter()
+            .map(|column| format!("The `{}` column", column))
+            .collect();
+        quote! {
+            #[doc = #enum_doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #vis enum #field_enum {
+                #(#[doc = #variant_docs] #variants),*
+            }
 
-                let mut result = Vec::with_capacity(items.len());
+            impl #field_enum {
+                /// Every field, in declaration order
+                pub const ALL: &'static [Self] = &[#(Self::#variants),*];
+            }
 
-                for item_value in items {
-                    let item = item_value.as_array()
-                        .ok_or_else(|| "Item is not an array")?;
+            impl ::ts_model::TsField for #field_enum {
+                fn column_name(self) -> &'static str {
+                    match self {
+                        #(Self::#variants => #column_names),*
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
 
-                    #(#field_parsers)*
+    // Model returned for a partial selection: the model itself when every
+    // field defaults, otherwise a generated `{Model}Partial` with optional fields
+    let (partial_type, partial_def) = if partial_of.is_some() || response_opts.default_all {
+        (quote! { Self }, quote! {})
+    } else {
+        let partial_name = format_ident!("{}Partial", name);
+        let partial_doc = format!(
+            "[`{}`] with every field optional, returned for a partial selection of fields",
+            name
+        );
+        let response_attrs = input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("response"));
+        let partial_derive: Vec<_> = response_opts.partial_derive.iter().collect();
+        let derive_serialize = partial_derive.iter().any(|path| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Serialize")
+        });
+        let partial_fields: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let field_vis = &field.vis;
+                let field_name = field.ident.as_ref().unwrap();
+                let field_type = &field.ty;
+                let field_type = if field_type_is_option(field_type) {
+                    quote! { #field_type }
+                } else {
+                    quote! { Option<#field_type> }
+                };
+                let docs = field
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("doc"));
+                let ts_field = field
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("ts_field"));
+                let skip_none = derive_serialize
+                    .then(|| quote! { #[serde(skip_serializing_if = "Option::is_none")] });
+                (
+                    quote! {
+                        #(#docs)*
+                        #skip_none
+                        #field_vis #field_name: #field_type
+                    },
+                    quote! { #(#ts_field)* #field_name: #field_type },
+                )
+            })
+            .collect();
+        let (struct_fields, derive_fields): (Vec<_>, Vec<_>) = partial_fields.into_iter().unzip();
+
+        // Expanded like any response model, minus the serde attributes
+        let partial_input: DeriveInput = syn::parse_quote! {
+            #(#response_attrs)*
+            #vis struct #partial_name {
+                #(#derive_fields),*
+            }
+        };
+        let partial_impl = expand_response(&partial_input, Some(name));
 
-                    result.push(Self {
-                        #struct_field_tokens
-                    });
+        (
+            quote! { #partial_name },
+            quote! {
+                #[doc = #partial_doc]
+                #[derive(Default, #(#partial_derive),*)]
+                #vis struct #partial_name {
+                    #(#struct_fields),*
                 }
 
-                Ok(result)
-            }
+                #partial_impl
+            },
+        )
+    };
+
+    // Generate implementation for parsing response
+    let output = quote! {
+        #field_enum_def
+        #partial_def
+
+        #[doc(hidden)]
+        #[derive(Default)]
+        #vis struct #builder_name {
+            #(#builder_slots),*
+        }
 
-            /// Get the API name for this response
-            pub fn api_name() -> &'static str {
+        impl ::ts_model::TsRow for #name {
+            type Builder = #builder_name;
+            type Field = #field_enum;
+            type Partial = #partial_type;
+
+            fn api_name() -> &'static str {
                 #api_name
             }
 
-            /// Get field names from the response struct
-            pub fn get_field_names() -> Vec<&'static str> {
+            fn field_names() -> Vec<&'static str> {
                 vec![
-                    #(stringify!(#field_names)),*
+                    #(#column_names),*
                 ]
             }
+
+            fn resolve_columns(fields: Option<&[&str]>) -> Result<Vec<Option<usize>>, ::ts_model::TsError> {
+                let fields = match fields {
+                    Some(fields) => fields,
+                    None => #missing_fields,
+                };
+
+                // Map column names to their position in each item
+                let columns: std::collections::HashMap<&str, usize> = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field)| (*field, index))
+                    .collect();
+
+                #(#column_lookups)*
+
+                Ok(vec![#(#columns),*])
+            }
+
+            fn from_row(columns: &[Option<usize>], item: &[serde_json::Value]) -> Result<Self, ::ts_model::TsError> {
+                #(#field_parsers)*
+
+                Ok(Self {
+                    #struct_field_tokens
+                })
+            }
+
+            fn deserialize_cell<'de, D: serde::Deserializer<'de>>(
+                __builder: &mut Self::Builder,
+                field: usize,
+                cell: D,
+            ) -> Result<(), D::Error> {
+                match field {
+                    #(#cell_arms,)*
+                    _ => {
+                        <serde::de::IgnoredAny as serde::Deserialize>::deserialize(cell)?;
+                    }
+                }
+                Ok(())
+            }
+
+            fn build(__builder: Self::Builder, columns: &[Option<usize>]) -> Result<Self, ::ts_model::TsError> {
+                #(#field_builders)*
+
+                Ok(Self {
+                    #struct_field_tokens
+                })
+            }
         }
 
-        // Implement From<Value> to allow automatic conversion from JSON
-        impl From<serde_json::Value> for #name {
-            fn from(value: serde_json::Value) -> Self {
-                // This is just a placeholder implementation to satisfy the trait bound
-                // The actual conversion is handled by the from_json method
-                panic!("Direct conversion from Value to {} is not supported, use from_json instead", stringify!(#name));
+        impl TryFrom<&serde_json::Value> for #name {
+            type Error = ::ts_model::TsError;
+
+            /// Parse a single row, either an object keyed by column name or an array in field order
+            fn try_from(row: &serde_json::Value) -> Result<Self, Self::Error> {
+                ::ts_model::row::row_from_value(row)
             }
         }
+
+        impl #name {
+            /// Get the column names with the doc comment of their field
+            pub fn field_docs() -> Vec<(&'static str, &'static str)> {
+                vec![
+                    #((#column_names, #column_docs)),*
+                ]
+            }
+        }
+
+        #registry_entry
     };
 
-    output.into()
+    output
+}
+
+/// A type as written in the source, e.g. `Option<NaiveDate>`
+fn type_name(ty: &Type) -> String {
+    quote!(#ty)
+        .to_string()
+        .replace(" < ", "<")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace(" :: ", "::")
+}
+
+/// The `CamelCase` enum variant for a `snake_case` field
+fn variant_name(field: &syn::Ident) -> syn::Ident {
+    let field = field.to_string();
+    let field = field.trim_start_matches("r#");
+    let mut variant = String::new();
+    for part in field.split('_').filter(|part| !part.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            variant.extend(first.to_uppercase());
+            variant.push_str(chars.as_str());
+        }
+    }
+    format_ident!("{}", variant)
+}
+
+/// Check if a type is `NaiveDate` or `Option<NaiveDate>`
+fn field_type_is_date(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    if segment.ident == "NaiveDate" {
+        return true;
+    }
+    if segment.ident != "Option" {
+        return false;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter().any(
+            |arg| matches!(arg, syn::GenericArgument::Type(inner) if field_type_is_date(inner)),
+        ),
+        _ => false,
+    }
 }
 
 /// Check if a type is an Option<T>
@@ -0,0 +1,365 @@
+//! A local stand-in for the Tushare HTTP API
+//!
+//! Answers Tushare's POST protocol (`api_name`, `token`, `params`, `fields`)
+//! from fixture files named `<api_name>.json`, each holding a full response
+//! body. Point a client at it with `TUSHARE_BASE_URL=http://127.0.0.1:7777/`.
+//!
+//! Error codes can be injected for an API with `--fail kpl_list=40203`, or for
+//! every call made with a token of the form `error:2002`.
+
+use std::{collections::HashMap, fs, io, path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use poem::{
+    handler, listener::TcpListener, post, web::Data, web::Json, Endpoint, EndpointExt, Route,
+    Server,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    /// Port to listen on
+    #[arg(long, default_value_t = 7777)]
+    port: u16,
+    /// Directory of `<api_name>.json` response bodies, may be repeated
+    #[arg(long, default_value = "ts-model/fixtures")]
+    fixtures: Vec<PathBuf>,
+    /// Answer an API with an error code instead of its fixture, e.g. `kpl_list=40203`
+    #[arg(long, value_parser = parse_failure)]
+    fail: Vec<(String, i64)>,
+}
+
+fn parse_failure(value: &str) -> Result<(String, i64), String> {
+    let (api_name, code) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected API=CODE, got '{}'", value))?;
+    let code = code
+        .parse()
+        .map_err(|_| format!("invalid error code '{}'", code))?;
+    Ok((api_name.to_string(), code))
+}
+
+/// The body Tushare expects, `fields` being a comma separated string
+#[derive(Debug, Deserialize)]
+struct TsRequest {
+    api_name: String,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    params: Map<String, Value>,
+    #[serde(default)]
+    fields: Option<String>,
+}
+
+/// Fixtures and injected failures shared by every request
+#[derive(Debug, Default)]
+struct MockState {
+    fixtures: HashMap<String, Value>,
+    failures: HashMap<String, i64>,
+}
+
+impl MockState {
+    /// Load every `*.json` file of `dirs`, later directories overriding earlier ones
+    fn load(dirs: &[PathBuf]) -> io::Result<Self> {
+        let mut fixtures = HashMap::new();
+        for dir in dirs {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                let Some(api_name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+
+                let body: Value = serde_json::from_slice(&fs::read(&path)?)
+                    .map_err(|e| io::Error::other(format!("{}: {}", path.display(), e)))?;
+                fixtures.insert(api_name.to_string(), body);
+            }
+        }
+
+        Ok(Self {
+            fixtures,
+            failures: HashMap::new(),
+        })
+    }
+
+    fn respond(&self, request: &TsRequest) -> Value {
+        if request.token.is_empty() {
+            return error_body(40101);
+        }
+        if let Some(code) = request
+            .token
+            .strip_prefix("error:")
+            .and_then(|code| code.parse().ok())
+        {
+            return error_body(code);
+        }
+        if let Some(code) = self.failures.get(&request.api_name) {
+            return error_body(*code);
+        }
+
+        let Some(fixture) = self.fixtures.get(&request.api_name) else {
+            return error_body(-2001);
+        };
+        let columns: Vec<&str> = fixture
+            .pointer("/data/fields")
+            .and_then(Value::as_array)
+            .map(|fields| fields.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let items: Vec<&Vec<Value>> = fixture
+            .pointer("/data/items")
+            .and_then(Value::as_array)
+            .map(|items| items.iter().filter_map(Value::as_array).collect())
+            .unwrap_or_default();
+
+        // Params naming a column select the rows with that value
+        let filters: Vec<(usize, &str)> = request
+            .params
+            .iter()
+            .filter_map(|(name, value)| {
+                let index = columns.iter().position(|column| column == name)?;
+                value
+                    .as_str()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (index, value))
+            })
+            .collect();
+        let items: Vec<_> = items
+            .into_iter()
+            .filter(|item| {
+                filters.iter().all(|(index, value)| match item.get(*index) {
+                    Some(Value::String(cell)) => cell == value,
+                    Some(cell) => cell.to_string().as_str() == *value,
+                    None => false,
+                })
+            })
+            .collect();
+
+        // Project onto the requested fields, unknown names being ignored like Tushare does
+        let selected: Vec<usize> = match request.fields.as_deref() {
+            Some(fields) if !fields.trim().is_empty() => fields
+                .split(',')
+                .filter_map(|field| columns.iter().position(|column| *column == field.trim()))
+                .collect(),
+            _ => (0..columns.len()).collect(),
+        };
+
+        let offset = usize_param(&request.params, "offset").unwrap_or(0);
+        let limit = usize_param(&request.params, "limit").unwrap_or(items.len());
+        let page: Vec<Value> = items
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|item| {
+                selected
+                    .iter()
+                    .map(|index| item.get(*index).cloned().unwrap_or(Value::Null))
+                    .collect()
+            })
+            .collect();
+
+        json!({
+            "request_id": "mock",
+            "code": 0,
+            "msg": "",
+            "data": {
+                "fields": selected.iter().map(|index| columns[*index]).collect::<Vec<_>>(),
+                "items": page,
+                "has_more": offset + page.len() < items.len(),
+            }
+        })
+    }
+}
+
+/// A param Tushare accepts either as a number or as a numeric string
+fn usize_param(params: &Map<String, Value>, name: &str) -> Option<usize> {
+    match params.get(name)? {
+        Value::Number(number) => number.as_u64().map(|number| number as usize),
+        Value::String(number) => number.parse().ok(),
+        _ => None,
+    }
+}
+
+/// A failed response with the message Tushare sends for `code`
+fn error_body(code: i64) -> Value {
+    let msg = match code {
+        40101 => "抱歉，您的token不对，请确认",
+        40203 => "抱歉，您每分钟最多访问该接口200次",
+        2002 => "抱歉，您没有访问该接口的权限",
+        -2001 => "请指定正确的接口名",
+        _ => "mock error",
+    };
+    json!({
+        "request_id": "mock",
+        "code": code,
+        "msg": msg,
+        "data": null,
+    })
+}
+
+#[handler]
+fn query(state: Data<&Arc<MockState>>, Json(request): Json<TsRequest>) -> Json<Value> {
+    tracing::info!(api_name = %request.api_name, params = ?request.params, "query");
+    Json(state.respond(&request))
+}
+
+fn app(state: MockState) -> impl Endpoint {
+    Route::new().at("/", post(query)).data(Arc::new(state))
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    tracing_subscriber::fmt().init();
+
+    let cli = Cli::parse();
+    let mut state = MockState::load(&cli.fixtures)?;
+    state.failures.extend(cli.fail);
+    tracing::info!(apis = state.fixtures.len(), "loaded fixtures");
+
+    let listener = TcpListener::bind(format!("{}:{}", cli.host, cli.port));
+    Server::new(listener).run(app(state)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use poem::{
+        listener::{Acceptor, Listener},
+        test::TestClient,
+    };
+    use ts_model::{
+        KplConceptReq, LimitStepReq, RetryPolicy, StkMinsItemField, StkMinsReq, TsClient, TsError,
+    };
+
+    use super::*;
+
+    fn state() -> MockState {
+        let fixtures = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../ts-model/fixtures"));
+        let mut state = MockState::load(&[fixtures]).unwrap();
+        state.failures.insert("moneyflow_ths".to_string(), 2002);
+        state
+    }
+
+    #[tokio::test]
+    async fn test_fields_and_paging() {
+        let client = TestClient::new(app(state()));
+
+        let response = client
+            .post("/")
+            .body_json(&json!({
+                "api_name": "stk_mins",
+                "token": "token",
+                "params": {"ts_code": "600000.SH", "offset": 1, "limit": "1"},
+                "fields": "trade_time,close,unknown",
+            }))
+            .send()
+            .await;
+        response.assert_status_is_ok();
+        let body: Value = response.json().await.value().deserialize();
+        assert_eq!(body["data"]["fields"], json!(["trade_time", "close"]));
+        assert_eq!(
+            body["data"]["items"],
+            json!([["2025-04-07 09:32:00", 10.08]])
+        );
+        assert_eq!(body["data"]["has_more"], true);
+
+        // A param naming a column filters the rows
+        let request = TsRequest {
+            api_name: "limit_step".to_string(),
+            token: "token".to_string(),
+            params: json!({"ts_code": "603388.SH"})
+                .as_object()
+                .cloned()
+                .unwrap(),
+            fields: None,
+        };
+        let body = state().respond(&request);
+        assert_eq!(body["data"]["items"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let state = state();
+        let request = |api_name: &str, token: &str| TsRequest {
+            api_name: api_name.to_string(),
+            token: token.to_string(),
+            params: Map::new(),
+            fields: None,
+        };
+
+        assert_eq!(state.respond(&request("kpl_list", ""))["code"], 40101);
+        assert_eq!(
+            state.respond(&request("kpl_list", "error:40203"))["code"],
+            40203
+        );
+        assert_eq!(
+            state.respond(&request("moneyflow_ths", "token"))["code"],
+            2002
+        );
+        assert_eq!(state.respond(&request("daily", "token"))["code"], -2001);
+        assert_eq!(state.respond(&request("kpl_list", "token"))["code"], 0);
+        assert_eq!(
+            parse_failure("kpl_list=40203"),
+            Ok(("kpl_list".to_string(), 40203))
+        );
+        assert!(parse_failure("kpl_list").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_end_to_end() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app(state())));
+
+        let client = |token: &str| {
+            TsClient::builder()
+                .token(token)
+                .base_url(format!("http://{}/", addr))
+                .retry(RetryPolicy::none())
+                .build()
+                .unwrap()
+        };
+
+        let rows = StkMinsReq {
+            ts_code: "600000.SH".to_string(),
+            freq: "1min".to_string(),
+            start_date: None,
+            end_date: None,
+        }
+        .with_fields([StkMinsItemField::TradeTime, StkMinsItemField::Close])
+        .page_size(2)
+        .execute_partial_all_with(&client("token"))
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].close, Some(10.1));
+        assert_eq!(rows[2].open, None);
+
+        let rows = LimitStepReq {
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
+            nums: String::new(),
+        }
+        .execute_typed_with(&client("token"))
+        .await
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let err = KplConceptReq {
+            trade_date: "20250407".to_string(),
+        }
+        .execute_typed_with(&client("error:2002"))
+        .await
+        .unwrap_err();
+        assert!(matches!(err, TsError::Permission { code: 2002, .. }));
+    }
+}
@@ -0,0 +1,82 @@
+//! Compares decoding large `stk_mins` and `kpl_list` bodies through
+//! `serde_json::Value` against the single-pass `RowPage::decode`.
+//!
+//! `KplListItem` uses `#[response(default_all)]`, covering the
+//! `#[serde(default)]` path of the decoder.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+use ts_model::{KplListItem, RowPage, StkMinsItem, TsRow};
+
+fn stk_mins_body(rows: usize) -> Vec<u8> {
+    let items: Vec<Value> = (0..rows)
+        .map(|i| {
+            json!([
+                "600000.SH",
+                format!("2025-04-07 {:02}:{:02}:00", 9 + i / 60 % 6, i % 60),
+                10.0 + i as f64 * 0.01,
+                10.1,
+                10.2,
+                9.9,
+                1000 + i as i64,
+                123456.78
+            ])
+        })
+        .collect();
+    let body = json!({
+        "code": 0,
+        "msg": "",
+        "data": {
+            "fields": StkMinsItem::field_names(),
+            "items": items,
+            "has_more": false
+        }
+    });
+    serde_json::to_vec(&body).unwrap()
+}
+
+/// The `kpl_list` fixture with its rows repeated up to `rows`
+fn kpl_list_body(rows: usize) -> Vec<u8> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/kpl_list.json");
+    let mut body: Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    let items = body["data"]["items"].as_array().unwrap().clone();
+    body["data"]["items"] = items.iter().cycle().take(rows).cloned().collect();
+    serde_json::to_vec(&body).unwrap()
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let body = stk_mins_body(100_000);
+    let mut group = c.benchmark_group("stk_mins_100k");
+    group.sample_size(10);
+
+    group.bench_function("value_from_json", |b| {
+        b.iter(|| {
+            let json: Value = serde_json::from_slice(black_box(&body)).unwrap();
+            StkMinsItem::from_json(&json).unwrap()
+        })
+    });
+    group.bench_function("single_pass", |b| {
+        b.iter(|| RowPage::<StkMinsItem>::decode(black_box(&body)).unwrap())
+    });
+
+    group.finish();
+
+    let body = kpl_list_body(100_000);
+    let mut group = c.benchmark_group("kpl_list_100k");
+    group.sample_size(10);
+
+    group.bench_function("value_from_json", |b| {
+        b.iter(|| {
+            let json: Value = serde_json::from_slice(black_box(&body)).unwrap();
+            KplListItem::from_json(&json).unwrap()
+        })
+    });
+    group.bench_function("single_pass", |b| {
+        b.iter(|| RowPage::<KplListItem>::decode(black_box(&body)).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+use std::future::Future;
+
+use serde_json::{Map, Value};
+
+use crate::{EndpointConfig, Pagination, TsClient, TsError, TsRow, DEFAULT_PAGE_SIZE};
+
+/// A typed Tushare endpoint, implemented by `#[derive(TsEndpoint)]` when `resp` is set
+///
+/// Lets cross-cutting code (caching, batching, scheduling, tools) be written
+/// once against `E: TsApi` instead of per request struct:
+///
+/// ```ignore
+/// async fn fetch<E: TsApi + Sync>(client: &TsClient, e: E) -> Vec<E::Response> {
+///     e.fetch(client).await.unwrap_or_default()
+/// }
+/// ```
+pub trait TsApi {
+    /// The Tushare `api_name`
+    const API_NAME: &'static str;
+    /// Human readable description of the endpoint
+    const DESCRIPTION: &'static str;
+    /// Rows requested per call by the paged methods
+    const PAGE_SIZE: usize = DEFAULT_PAGE_SIZE;
+
+    /// The model every returned row is parsed into
+    type Response: TsRow + Send + 'static;
+
+    /// The request params sent to Tushare
+    fn params(&self) -> Result<Map<String, Value>, TsError>;
+
+    /// Rate limit and retry settings declared on the endpoint
+    fn config() -> EndpointConfig {
+        EndpointConfig::default()
+    }
+
+    /// Fetch one page of typed rows
+    fn fetch(
+        &self,
+        client: &TsClient,
+    ) -> impl Future<Output = Result<Vec<Self::Response>, TsError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let fields = Self::Response::field_names();
+            client
+                .call_typed(
+                    Self::API_NAME,
+                    self.params()?,
+                    Some(&fields),
+                    Self::config(),
+                )
+                .await
+        }
+    }
+
+    /// Fetch every page of typed rows
+    fn fetch_all(
+        &self,
+        client: &TsClient,
+    ) -> impl Future<Output = Result<Vec<Self::Response>, TsError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let fields = Self::Response::field_names();
+            client
+                .call_all_typed(
+                    Self::API_NAME,
+                    self.params()?,
+                    Some(&fields),
+                    Pagination::new(Self::PAGE_SIZE),
+                    Self::config(),
+                )
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn describe<E: TsApi>(request: &E) -> (&'static str, usize, usize) {
+        let params = request.params().unwrap();
+        (E::API_NAME, E::PAGE_SIZE, params.len())
+    }
+
+    #[test]
+    fn test_generic_endpoint() {
+        let request = StkMinsReq {
+            ts_code: "600000.SH".to_string(),
+            freq: "1min".to_string(),
+            start_date: None,
+            end_date: None,
+        };
+        assert_eq!(describe(&request), ("stk_mins", 8000, 2));
+        assert_eq!(<StkMinsReq as TsApi>::DESCRIPTION, "获取A股分钟数据");
+        assert_eq!(
+            <StkMinsReq as TsApi>::Response::field_names(),
+            StkMinsItem::field_names()
+        );
+
+        let request = KplConceptReq {
+            trade_date: "20250407".to_string(),
+        };
+        assert_eq!(describe(&request), ("kpl_concept", DEFAULT_PAGE_SIZE, 1));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_error() {
+        let client = TsClient::builder()
+            .token("token")
+            .base_url("http://127.0.0.1:1/")
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let request = KplConceptReq {
+            trade_date: "20250407".to_string(),
+        };
+
+        let err = request.fetch(&client).await.unwrap_err();
+        assert_eq!(err.api_name(), Some("kpl_concept"));
+    }
+}
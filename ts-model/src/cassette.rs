@@ -0,0 +1,278 @@
+//! Record/replay of Tushare requests
+//!
+//! A cassette is a JSON file holding every `(api_name, params, fields)` request
+//! together with the response body Tushare returned for it:
+//!
+//! ```text
+//! {"interactions": [{"request": {"api_name": .., "params": {..}, "fields": [..]}, "response": {..}}]}
+//! ```
+//!
+//! Record a trading day once with a real token, then replay it without one:
+//!
+//! ```text
+//! TUSHARE_CASSETTE=20250407.json TUSHARE_CASSETTE_MODE=record tsrs-mcp-server
+//! TUSHARE_CASSETTE=20250407.json tsrs-mcp-server
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{transport::params_key, Transport, TsError, TsRequest};
+
+/// Whether a cassette is written or read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Send requests through the HTTP API and write them with their responses
+    Record,
+    /// Answer requests from the cassette, failing on unknown ones
+    Replay,
+}
+
+impl FromStr for CassetteMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_ascii_lowercase().as_str() {
+            "record" => Ok(CassetteMode::Record),
+            "replay" => Ok(CassetteMode::Replay),
+            _ => Err(format!("unknown cassette mode '{}'", mode)),
+        }
+    }
+}
+
+/// One recorded request and the response body it got
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request, without the token
+    pub request: TsRequest,
+    /// The response body as returned by Tushare
+    pub response: Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+/// A [`Transport`] recording to or replaying from a cassette file
+///
+/// Requests match when their `api_name`, params and fields are all equal.
+#[derive(Debug)]
+pub struct CassetteTransport {
+    path: PathBuf,
+    /// The transport requests are recorded from, `None` when replaying
+    inner: Option<Arc<dyn Transport>>,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Held while the file is written, so snapshots land in order
+    file: tokio::sync::Mutex<()>,
+}
+
+impl CassetteTransport {
+    /// Record the requests sent through `inner` into `path`
+    ///
+    /// Interactions already in the file are kept, a repeated request replacing
+    /// the earlier response. The file is rewritten after every request.
+    pub fn record(path: impl Into<PathBuf>, inner: Arc<dyn Transport>) -> Result<Self, TsError> {
+        let path = path.into();
+        let interactions = if path.exists() {
+            load(&path)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path,
+            inner: Some(inner),
+            interactions: Mutex::new(interactions),
+            file: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Replay the requests recorded in `path`
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, TsError> {
+        let path = path.into();
+        let interactions = load(&path)?;
+
+        Ok(Self {
+            path,
+            inner: None,
+            interactions: Mutex::new(interactions),
+            file: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// The interactions recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions.lock().unwrap().clone()
+    }
+
+    fn find(&self, request: &TsRequest) -> Option<Value> {
+        self.interactions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|interaction| same_request(&interaction.request, request))
+            .map(|interaction| interaction.response.clone())
+    }
+
+    async fn save(&self, request: &TsRequest, body: &[u8]) -> Result<(), TsError> {
+        let response: Value = match serde_json::from_slice(body) {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!(
+                    api_name = %request.api_name,
+                    error = %e,
+                    "not recording a response that is not JSON"
+                );
+                return Ok(());
+            }
+        };
+
+        let _file = self.file.lock().await;
+        let cassette = {
+            let mut interactions = self.interactions.lock().unwrap();
+            interactions.retain(|interaction| !same_request(&interaction.request, request));
+            interactions.push(Interaction {
+                request: request.clone(),
+                response,
+            });
+            Cassette {
+                interactions: interactions.clone(),
+            }
+        };
+
+        // Written outside the interactions lock, so other requests are not held up by the I/O
+        let json =
+            serde_json::to_vec_pretty(&cassette).map_err(|e| cassette_error(&self.path, e))?;
+        tokio::fs::write(&self.path, json)
+            .await
+            .map_err(|e| cassette_error(&self.path, e))
+    }
+}
+
+impl Transport for CassetteTransport {
+    fn send<'a>(&'a self, request: &'a TsRequest) -> BoxFuture<'a, Result<Vec<u8>, TsError>> {
+        Box::pin(async move {
+            match &self.inner {
+                Some(inner) => {
+                    let body = inner.send(request).await?;
+                    self.save(request, &body).await?;
+                    Ok(body)
+                }
+                None => {
+                    let response = self.find(request).ok_or_else(|| TsError::Unmatched {
+                        api_name: request.api_name.clone(),
+                        params: params_key(&request.params),
+                    })?;
+                    serde_json::to_vec(&response).map_err(|e| cassette_error(&self.path, e))
+                }
+            }
+        })
+    }
+}
+
+fn same_request(a: &TsRequest, b: &TsRequest) -> bool {
+    a.api_name == b.api_name
+        && a.fields == b.fields
+        && params_key(&a.params) == params_key(&b.params)
+}
+
+fn load(path: &Path) -> Result<Vec<Interaction>, TsError> {
+    let json = fs::read(path).map_err(|e| cassette_error(path, e))?;
+    let cassette: Cassette = serde_json::from_slice(&json).map_err(|e| cassette_error(path, e))?;
+    Ok(cassette.interactions)
+}
+
+fn cassette_error(path: &Path, reason: impl ToString) -> TsError {
+    TsError::Cassette {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{KplConceptReq, MockTransport, RetryPolicy, TsClient};
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path =
+            std::env::temp_dir().join(format!("ts-model-cassette-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let body = json!({
+            "code": 0,
+            "msg": "",
+            "data": {
+                "fields": ["trade_date", "ts_code", "name", "z_t_num", "up_num"],
+                "items": [["20250407", "000111.KP", "华为鸿蒙", 12, "3"]]
+            }
+        });
+        let mock = MockTransport::new().with_response(
+            "kpl_concept",
+            json!({"trade_date": "20250407"}),
+            body.clone(),
+        );
+        let request = || KplConceptReq {
+            trade_date: "20250407".to_string(),
+        };
+
+        let client = TsClient::builder()
+            .transport(mock.clone())
+            .cassette(&path, CassetteMode::Record)
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let recorded = request().execute_typed_with(&client).await.unwrap();
+        assert_eq!(mock.requests().len(), 1);
+
+        // Replaying needs no token and no network
+        let client = TsClient::builder()
+            .cassette(&path, CassetteMode::Replay)
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let replayed = request().execute_typed_with(&client).await.unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].ts_code, recorded[0].ts_code);
+        assert_eq!(mock.requests().len(), 1);
+
+        // Other params or fields are not in the cassette
+        let err = KplConceptReq {
+            trade_date: "20250408".to_string(),
+        }
+        .execute_typed_with(&client)
+        .await
+        .unwrap_err();
+        assert!(matches!(err, TsError::Unmatched { .. }));
+        let err = request().execute_with(&client).await.unwrap_err();
+        assert!(matches!(err, TsError::Unmatched { .. }));
+
+        let cassette = CassetteTransport::replay(&path).unwrap();
+        assert_eq!(cassette.interactions()[0].response, body);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            CassetteTransport::replay(&path),
+            Err(TsError::Cassette { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mode() {
+        assert_eq!("record".parse(), Ok(CassetteMode::Record));
+        assert_eq!("Replay".parse(), Ok(CassetteMode::Replay));
+        assert!("rewind".parse::<CassetteMode>().is_err());
+    }
+}
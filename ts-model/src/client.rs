@@ -0,0 +1,638 @@
+use std::{
+    env,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use futures::{stream as futures_stream, StreamExt, TryStreamExt};
+use serde_json::{Map, Value};
+
+use crate::{
+    stream, CassetteMode, CassetteTransport, HttpTransport, PageBody, Pagination, QueryPolicy,
+    RateLimiter, RetryPolicy, RowPage, Transport, TsError, TsRequest, TsRow, TsStream,
+    DEFAULT_RATE_LIMIT,
+};
+
+/// Default Tushare Pro endpoint
+pub const DEFAULT_BASE_URL: &str = "https://api.tushare.pro/";
+/// Default timeout of a single HTTP request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default `User-Agent` header
+pub const DEFAULT_USER_AGENT: &str = concat!("ts-model/", env!("CARGO_PKG_VERSION"));
+
+/// Per-endpoint settings declared through `#[endpoint(...)]`
+///
+/// Client-level overrides take precedence over these.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EndpointConfig {
+    /// Calls per minute allowed for the API
+    pub rate_limit: Option<u32>,
+    /// Retries of transient failures, unless the client sets its own retry policy
+    pub retry: Option<u32>,
+}
+
+/// Process-wide client used by `execute()` and friends
+static SHARED: OnceLock<TsClient> = OnceLock::new();
+
+/// A Tushare client owning a pooled HTTP client, the token and the base URL
+///
+/// Requests go through a [`Transport`], the HTTP API unless another one (e.g. a
+/// [`MockTransport`](crate::MockTransport)) is set on the builder. Cloning is
+/// cheap and all clones share the same connection pool.
+#[derive(Debug, Clone)]
+pub struct TsClient {
+    inner: Arc<ClientInner>,
+}
+
+#[derive(Debug)]
+struct ClientInner {
+    transport: Arc<dyn Transport>,
+    base_url: String,
+    rate_limiter: RateLimiter,
+    /// The policy set on the builder, which wins over the endpoint's `retry`
+    retry: Option<RetryPolicy>,
+    query_policy: QueryPolicy,
+}
+
+impl TsClient {
+    /// Create a client with the given token and default settings
+    pub fn new(token: impl Into<String>) -> Result<Self, TsError> {
+        Self::builder().token(token).build()
+    }
+
+    /// Create a client configured from the environment (and `.env`)
+    ///
+    /// Reads `TUSHARE_TOKEN` and the optional `TUSHARE_BASE_URL`.
+    pub fn from_env() -> Result<Self, TsError> {
+        Self::builder().from_env().build()
+    }
+
+    /// Start building a client
+    pub fn builder() -> TsClientBuilder {
+        TsClientBuilder::default()
+    }
+
+    /// The process-wide client, created from the environment on first use
+    pub fn shared() -> Result<Self, TsError> {
+        if let Some(client) = SHARED.get() {
+            return Ok(client.clone());
+        }
+
+        let client = Self::from_env()?;
+        Ok(SHARED.get_or_init(|| client).clone())
+    }
+
+    /// The URL requests are posted to
+    pub fn base_url(&self) -> &str {
+        &self.inner.base_url
+    }
+
+    /// The per-API rate limiter, e.g. to adjust limits at runtime
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.inner.rate_limiter
+    }
+
+    /// The APIs the `tushare_query` tool may call
+    pub fn query_policy(&self) -> &QueryPolicy {
+        &self.inner.query_policy
+    }
+
+    /// Call a Tushare API and return the raw response body
+    pub async fn call(
+        &self,
+        api_name: &str,
+        params: Map<String, Value>,
+        fields: Option<&[&str]>,
+    ) -> Result<Value, TsError> {
+        self.call_as(api_name, params, fields, EndpointConfig::default())
+            .await
+    }
+
+    /// Call a Tushare API and deserialize the rows straight from the response bytes
+    pub async fn call_typed<T>(
+        &self,
+        api_name: &str,
+        params: Map<String, Value>,
+        fields: Option<&[&str]>,
+        config: EndpointConfig,
+    ) -> Result<Vec<T>, TsError>
+    where
+        T: TsRow + Send + 'static,
+    {
+        let page: RowPage<T> = self.call_as(api_name, params, fields, config).await?;
+        Ok(page.rows)
+    }
+
+    /// Call a Tushare API with the settings of its endpoint and decode the body as `P`
+    ///
+    /// `P` is [`Value`] for the raw body. Waits for the rate limiter before
+    /// every attempt and retries transient failures according to the client's
+    /// [`RetryPolicy`]. Decoding happens inside the retry loop, so retryable
+    /// `code`s such as rate limit errors are retried as well.
+    pub async fn call_as<P: PageBody>(
+        &self,
+        api_name: &str,
+        params: Map<String, Value>,
+        fields: Option<&[&str]>,
+        config: EndpointConfig,
+    ) -> Result<P, TsError> {
+        let request = TsRequest {
+            api_name: api_name.to_string(),
+            params,
+            fields: fields.map(|fields| fields.iter().map(|field| field.to_string()).collect()),
+        };
+
+        let policy = match (self.inner.retry, config.retry) {
+            (Some(policy), _) => policy,
+            (None, Some(max_retries)) => RetryPolicy::default().max_retries(max_retries),
+            (None, None) => RetryPolicy::default(),
+        };
+
+        let mut retry = 0;
+        loop {
+            self.inner
+                .rate_limiter
+                .acquire(api_name, config.rate_limit)
+                .await;
+
+            let start = Instant::now();
+            let result = self
+                .inner
+                .transport
+                .send(&request)
+                .await
+                .and_then(|body| P::decode(api_name, &body));
+            match result {
+                Ok(page) => {
+                    tracing::debug!(
+                        api_name,
+                        retry,
+                        latency_ms = start.elapsed().as_millis() as u64,
+                        "request finished"
+                    );
+                    return Ok(page);
+                }
+                Err(err) => {
+                    retry += 1;
+                    if !policy.should_retry(&err, retry) {
+                        return Err(err);
+                    }
+
+                    let delay = policy.delay_for(&err, retry);
+                    tracing::warn!(
+                        api_name,
+                        retry,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "retrying request"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Call a Tushare API page by page until `has_more` is false
+    ///
+    /// `offset`/`limit` are added to `params`, and the `items` of all pages are
+    /// concatenated into the body of the first one.
+    pub async fn call_all(
+        &self,
+        api_name: &str,
+        params: Map<String, Value>,
+        fields: Option<&[&str]>,
+        pagination: Pagination,
+        config: EndpointConfig,
+    ) -> Result<Value, TsError> {
+        let fields = fields.map(|fields| fields.iter().map(|field| field.to_string()).collect());
+        let mut pages = self.pages_as::<Value>(api_name, params, fields, pagination, config);
+        let mut body: Option<Value> = None;
+        let mut items = Vec::new();
+
+        while let Some(mut page) = pages.try_next().await? {
+            if let Some(page_items) = page
+                .pointer_mut("/data/items")
+                .and_then(Value::as_array_mut)
+            {
+                items.append(page_items);
+            }
+            body.get_or_insert(page);
+        }
+
+        let mut body = body.unwrap_or_default();
+        if let Some(data) = body.get_mut("data").and_then(Value::as_object_mut) {
+            data.insert("items".to_string(), Value::Array(items));
+            data.insert("has_more".to_string(), Value::Bool(false));
+        }
+        Ok(body)
+    }
+
+    /// Lazily fetch typed rows page by page, deserializing each page in a single pass
+    pub fn typed_rows<T>(
+        &self,
+        api_name: impl Into<String>,
+        params: Map<String, Value>,
+        fields: Option<Vec<String>>,
+        pagination: Pagination,
+        config: EndpointConfig,
+    ) -> TsStream<T>
+    where
+        T: TsRow + Send + 'static,
+    {
+        let pages = self.pages_as::<RowPage<T>>(api_name, params, fields, pagination, config);
+        stream::page_rows(pages)
+    }
+
+    /// Fetch the typed rows of every page
+    pub async fn call_all_typed<T>(
+        &self,
+        api_name: &str,
+        params: Map<String, Value>,
+        fields: Option<&[&str]>,
+        pagination: Pagination,
+        config: EndpointConfig,
+    ) -> Result<Vec<T>, TsError>
+    where
+        T: TsRow + Send + 'static,
+    {
+        let fields = fields.map(|fields| fields.iter().map(|field| field.to_string()).collect());
+        self.typed_rows(api_name, params, fields, pagination, config)
+            .try_collect()
+            .await
+    }
+
+    /// Lazily fetch a Tushare API page by page, decoding every page as `P`
+    ///
+    /// The next page is only requested once the previous one has been consumed.
+    pub fn pages_as<P: PageBody>(
+        &self,
+        api_name: impl Into<String>,
+        params: Map<String, Value>,
+        fields: Option<Vec<String>>,
+        pagination: Pagination,
+        config: EndpointConfig,
+    ) -> TsStream<P> {
+        struct State {
+            client: TsClient,
+            api_name: String,
+            params: Map<String, Value>,
+            fields: Option<Vec<String>>,
+            pagination: Pagination,
+            config: EndpointConfig,
+            offset: usize,
+            done: bool,
+        }
+
+        let state = State {
+            client: self.clone(),
+            api_name: api_name.into(),
+            params,
+            fields,
+            pagination,
+            config,
+            offset: 0,
+            done: false,
+        };
+
+        futures_stream::try_unfold(state, |mut state| async move {
+            if state.done {
+                return Ok(None);
+            }
+
+            let fields: Option<Vec<&str>> = state
+                .fields
+                .as_ref()
+                .map(|fields| fields.iter().map(String::as_str).collect());
+            let page: P = state
+                .client
+                .call_page_as(
+                    &state.api_name,
+                    state.params.clone(),
+                    fields.as_deref(),
+                    state.offset,
+                    state.pagination,
+                    state.config,
+                )
+                .await?;
+
+            let len = page.len();
+            state.offset += len;
+            if state.offset > state.pagination.max_rows {
+                return Err(TsError::TooManyRows {
+                    api_name: state.api_name,
+                    max_rows: state.pagination.max_rows,
+                });
+            }
+            state.done = len == 0 || !page.has_more(state.pagination.page_size);
+
+            Ok(Some((page, state)))
+        })
+        .boxed()
+    }
+
+    /// Fetch a single page starting at `offset`, decoded as `P`
+    pub async fn call_page_as<P: PageBody>(
+        &self,
+        api_name: &str,
+        mut params: Map<String, Value>,
+        fields: Option<&[&str]>,
+        offset: usize,
+        pagination: Pagination,
+        config: EndpointConfig,
+    ) -> Result<P, TsError> {
+        params.insert("offset".to_string(), Value::from(offset));
+        params.insert("limit".to_string(), Value::from(pagination.page_size));
+        self.call_as(api_name, params, fields, config).await
+    }
+}
+
+/// Builder for [`TsClient`]
+#[derive(Debug, Clone)]
+pub struct TsClientBuilder {
+    token: Option<String>,
+    base_url: String,
+    timeout: Duration,
+    user_agent: String,
+    rate_limit: Option<u32>,
+    rate_limits: Vec<(String, Option<u32>)>,
+    retry: Option<RetryPolicy>,
+    transport: Option<Arc<dyn Transport>>,
+    /// The cassette path and mode, the mode failing when read from a bad env var
+    cassette: Option<(PathBuf, Result<CassetteMode, String>)>,
+    query_policy: QueryPolicy,
+}
+
+impl Default for TsClientBuilder {
+    fn default() -> Self {
+        Self {
+            token: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            rate_limit: Some(DEFAULT_RATE_LIMIT),
+            rate_limits: Vec::new(),
+            retry: None,
+            transport: None,
+            cassette: None,
+            query_policy: QueryPolicy::default(),
+        }
+    }
+}
+
+impl TsClientBuilder {
+    /// Fill in settings from `TUSHARE_TOKEN`, `TUSHARE_BASE_URL`, `TUSHARE_RATE_LIMIT`,
+    /// `TUSHARE_MAX_RETRIES`, `TUSHARE_CASSETTE`, `TUSHARE_CASSETTE_MODE`,
+    /// `TUSHARE_QUERY_ALLOW` and `TUSHARE_QUERY_DENY`
+    ///
+    /// A token already set on the builder is kept.
+    pub fn from_env(mut self) -> Self {
+        dotenvy::dotenv().ok();
+
+        if self.token.is_none() {
+            self.token = env::var("TUSHARE_TOKEN").ok();
+        }
+        if let Ok(base_url) = env::var("TUSHARE_BASE_URL") {
+            self.base_url = base_url;
+        }
+        if let Some(rate_limit) = env::var("TUSHARE_RATE_LIMIT")
+            .ok()
+            .and_then(|rate_limit| rate_limit.parse().ok())
+        {
+            // 0 disables the limiter
+            self.rate_limit = Some(rate_limit).filter(|rate_limit| *rate_limit > 0);
+        }
+        if let Some(max_retries) = env::var("TUSHARE_MAX_RETRIES")
+            .ok()
+            .and_then(|max_retries| max_retries.parse().ok())
+        {
+            self.retry = Some(self.retry.unwrap_or_default().max_retries(max_retries));
+        }
+        if let Ok(path) = env::var("TUSHARE_CASSETTE") {
+            // Replay unless asked to record, an unknown mode failing in `build`
+            let mode = match env::var("TUSHARE_CASSETTE_MODE") {
+                Ok(mode) => mode.parse(),
+                Err(_) => Ok(CassetteMode::Replay),
+            };
+            self.cassette = Some((path.into(), mode));
+        }
+        let allow = env::var("TUSHARE_QUERY_ALLOW").ok();
+        let deny = env::var("TUSHARE_QUERY_DENY").ok();
+        if allow.is_some() || deny.is_some() {
+            self.query_policy = QueryPolicy::from_lists(allow.as_deref(), deny.as_deref());
+        }
+        self
+    }
+
+    /// Set the Tushare token
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Override the endpoint, e.g. for a proxy or a local mock server
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Set the timeout of a single HTTP request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the `User-Agent` header
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Set the default calls per minute for every API, `None` meaning unlimited
+    pub fn rate_limit(mut self, per_minute: Option<u32>) -> Self {
+        self.rate_limit = per_minute;
+        self
+    }
+
+    /// Override the calls per minute of one API, `None` meaning unlimited
+    pub fn rate_limit_for(mut self, api_name: impl Into<String>, per_minute: Option<u32>) -> Self {
+        self.rate_limits.push((api_name.into(), per_minute));
+        self
+    }
+
+    /// Set the retry policy applied to every API
+    ///
+    /// Replaces the `retry` declared on endpoints, which otherwise adjusts the
+    /// retries of the default policy.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send requests through `transport` instead of the HTTP API
+    ///
+    /// No token is needed then.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Set the APIs the `tushare_query` tool may call
+    pub fn query_policy(mut self, query_policy: QueryPolicy) -> Self {
+        self.query_policy = query_policy;
+        self
+    }
+
+    /// Record requests to or replay them from the cassette at `path`
+    ///
+    /// Recording goes through the HTTP API (or the transport set with
+    /// [`TsClientBuilder::transport`]); replaying needs no token.
+    pub fn cassette(mut self, path: impl Into<PathBuf>, mode: CassetteMode) -> Self {
+        self.cassette = Some((path.into(), Ok(mode)));
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<TsClient, TsError> {
+        let transport: Arc<dyn Transport> = match &self.cassette {
+            Some((path, Ok(CassetteMode::Replay))) => Arc::new(CassetteTransport::replay(path)?),
+            Some((path, Ok(CassetteMode::Record))) => {
+                Arc::new(CassetteTransport::record(path, self.base_transport()?)?)
+            }
+            Some((path, Err(reason))) => {
+                return Err(TsError::Cassette {
+                    path: path.display().to_string(),
+                    reason: reason.clone(),
+                })
+            }
+            None => self.base_transport()?,
+        };
+
+        let rate_limiter = RateLimiter::new(self.rate_limit);
+        for (api_name, per_minute) in self.rate_limits {
+            rate_limiter.set_limit(api_name, per_minute);
+        }
+
+        Ok(TsClient {
+            inner: Arc::new(ClientInner {
+                transport,
+                base_url: self.base_url,
+                rate_limiter,
+                retry: self.retry,
+                query_policy: self.query_policy,
+            }),
+        })
+    }
+
+    /// The transport set on the builder, or the HTTP API
+    fn base_transport(&self) -> Result<Arc<dyn Transport>, TsError> {
+        if let Some(transport) = &self.transport {
+            return Ok(transport.clone());
+        }
+
+        let token = self
+            .token
+            .clone()
+            .filter(|token| !token.is_empty())
+            .ok_or(TsError::MissingToken)?;
+
+        let http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .user_agent(self.user_agent.clone())
+            .build()
+            .map_err(TsError::Client)?;
+        Ok(Arc::new(HttpTransport::new(
+            http,
+            token,
+            self.base_url.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{MockTransport, DEFAULT_RATE_LIMIT_DELAY};
+
+    #[test]
+    fn test_builder() {
+        let client = TsClient::builder()
+            .token("token")
+            .base_url("http://127.0.0.1:7777/")
+            .timeout(Duration::from_secs(5))
+            .rate_limit_for("stk_mins", Some(30))
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url(), "http://127.0.0.1:7777/");
+        assert_eq!(
+            client.rate_limiter().limit_for("stk_mins", Some(500)),
+            Some(30)
+        );
+        assert_eq!(
+            client.rate_limiter().limit_for("kpl_list", None),
+            Some(DEFAULT_RATE_LIMIT)
+        );
+
+        let client = TsClient::new("token").unwrap();
+        assert_eq!(client.base_url(), DEFAULT_BASE_URL);
+
+        assert!(matches!(
+            TsClient::builder().token("").build(),
+            Err(TsError::MissingToken)
+        ));
+
+        // A mistyped TUSHARE_CASSETTE_MODE fails instead of falling back to replay
+        let builder = TsClientBuilder {
+            cassette: Some(("day.json".into(), "recrod".parse())),
+            ..TsClient::builder().token("token")
+        };
+        match builder.build() {
+            Err(TsError::Cassette { reason, .. }) => {
+                assert_eq!(reason, "unknown cassette mode 'recrod'")
+            }
+            other => panic!("expected a cassette error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry() {
+        let transport = MockTransport::new().with_response(
+            "kpl_list",
+            json!({}),
+            json!({"code": 40203, "msg": "抱歉，您每分钟最多访问该接口200次"}),
+        );
+        let config = EndpointConfig {
+            rate_limit: None,
+            retry: Some(1),
+        };
+
+        // Without a client policy the endpoint's `retry` applies, and a rate
+        // limited call waits for the window to pass
+        let client = TsClient::builder()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let start = tokio::time::Instant::now();
+        let err = client
+            .call_as::<Value>("kpl_list", Map::new(), None, config)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TsError::RateLimited { .. }));
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(start.elapsed(), DEFAULT_RATE_LIMIT_DELAY);
+
+        // The client's policy wins over the endpoint's
+        let client = TsClient::builder()
+            .transport(transport.clone())
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert!(client
+            .call_as::<Value>("kpl_list", Map::new(), None, config)
+            .await
+            .is_err());
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
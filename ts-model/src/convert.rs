@@ -0,0 +1,256 @@
+//! Converters for `#[ts_field(with = "...")]` and date request params
+//!
+//! Tushare returns dates and times as strings such as `20250407`, `09:31:00` or
+//! `2025-04-07 09:31:00`. A converter turns such a cell into a typed value:
+//!
+//! ```ignore
+//! #[derive(TsResponse)]
+//! #[response(api = "stk_mins")]
+//! struct Bar {
+//!     #[ts_field(with = "datetime")]
+//!     trade_time: NaiveDateTime,
+//!     #[ts_field(with = "yyyymmdd")]
+//!     trade_date: Option<NaiveDate>,
+//! }
+//! ```
+//!
+//! Besides the built-in names (`yyyymmdd`, `hhmmss`, `datetime`, `shanghai`),
+//! `with` accepts the path of any type implementing [`Convert`].
+
+use std::{borrow::Cow, fmt};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::Value;
+
+use crate::de::Scalar;
+
+/// Parses the text of a cell into a typed value
+pub trait Convert {
+    /// The type of the field
+    type Target;
+
+    /// Parse a non-empty cell; numbers are passed in their decimal form
+    fn parse(value: &str) -> Result<Self::Target, String>;
+}
+
+/// `YYYYMMDD` (or `YYYY-MM-DD`) into [`NaiveDate`]
+pub struct Yyyymmdd;
+
+impl Convert for Yyyymmdd {
+    type Target = NaiveDate;
+
+    fn parse(value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+            .map_err(|e| format!("invalid date '{}': {}", value, e))
+    }
+}
+
+/// `HH:MM:SS` (or `HHMMSS`, `HH:MM`) into [`NaiveTime`]
+pub struct Hhmmss;
+
+impl Convert for Hhmmss {
+    type Target = NaiveTime;
+
+    fn parse(value: &str) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(value, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(value, "%H%M%S"))
+            .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+            .map_err(|e| format!("invalid time '{}': {}", value, e))
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (or `YYYYMMDD HH:MM:SS`) into [`NaiveDateTime`], in exchange local time
+pub struct Datetime;
+
+impl Convert for Datetime {
+    type Target = NaiveDateTime;
+
+    fn parse(value: &str) -> Result<NaiveDateTime, String> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y%m%d %H:%M:%S"))
+            .map_err(|e| format!("invalid datetime '{}': {}", value, e))
+    }
+}
+
+/// Like [`Datetime`] but attached to Asia/Shanghai, which is UTC+8 all year
+pub struct Shanghai;
+
+impl Convert for Shanghai {
+    type Target = DateTime<FixedOffset>;
+
+    fn parse(value: &str) -> Result<DateTime<FixedOffset>, String> {
+        let local = Datetime::parse(value)?;
+        shanghai()
+            .from_local_datetime(&local)
+            .single()
+            .ok_or_else(|| format!("invalid datetime '{}'", value))
+    }
+}
+
+/// The fixed UTC+8 offset of Asia/Shanghai
+pub fn shanghai() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+/// Deserialize a required cell with converter `C`
+pub fn deserialize<'de, C, D>(deserializer: D) -> Result<C::Target, D::Error>
+where
+    C: Convert,
+    D: Deserializer<'de>,
+{
+    match deserializer.deserialize_any(CellVisitor)? {
+        Some(value) if !value.is_empty() => C::parse(&value).map_err(de::Error::custom),
+        _ => Err(de::Error::custom("empty value")),
+    }
+}
+
+/// Deserialize an optional cell with converter `C`, `null` and `""` being `None`
+pub fn deserialize_option<'de, C, D>(deserializer: D) -> Result<Option<C::Target>, D::Error>
+where
+    C: Convert,
+    D: Deserializer<'de>,
+{
+    match deserializer.deserialize_any(CellVisitor)? {
+        Some(value) if !value.is_empty() => C::parse(&value).map(Some).map_err(de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Deserialize a `#[serde(default)]` cell with converter `C`
+///
+/// `null`, `""` and cells that fail to convert become `C::Target::default()`.
+pub fn deserialize_or_default<'de, C, D>(deserializer: D) -> Result<C::Target, D::Error>
+where
+    C: Convert,
+    C::Target: Default,
+    D: Deserializer<'de>,
+{
+    let value = match Scalar::deserialize(deserializer)? {
+        Scalar::Str(value) if !value.is_empty() => C::parse(&value).ok(),
+        Scalar::I64(value) => C::parse(&value.to_string()).ok(),
+        Scalar::U64(value) => C::parse(&value.to_string()).ok(),
+        _ => None,
+    };
+    Ok(value.unwrap_or_default())
+}
+
+/// Reads a string or number cell as text, borrowing from the input when possible
+struct CellVisitor;
+
+impl<'de> Visitor<'de> for CellVisitor {
+    type Value = Option<Cow<'de, str>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, a number or null")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+        Ok(Some(Cow::Borrowed(value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Some(Cow::Owned(value.to_string())))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Some(Cow::Owned(value)))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Some(Cow::Owned(value.to_string())))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(Cow::Owned(value.to_string())))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+}
+
+/// Serializes request dates as `YYYYMMDD`, used by `#[derive(TsEndpoint)]` for
+/// `NaiveDate` and `Option<NaiveDate>` fields
+pub trait DateParam {
+    /// The param value sent to Tushare, `null` for `None`
+    fn to_param(&self) -> Value;
+}
+
+impl DateParam for NaiveDate {
+    fn to_param(&self) -> Value {
+        Value::String(self.format("%Y%m%d").to_string())
+    }
+}
+
+impl<T: DateParam> DateParam for Option<T> {
+    fn to_param(&self) -> Value {
+        self.as_ref().map_or(Value::Null, DateParam::to_param)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_converters() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 7).unwrap();
+        assert_eq!(Yyyymmdd::parse("20250407").unwrap(), date);
+        assert_eq!(Yyyymmdd::parse("2025-04-07").unwrap(), date);
+        assert!(Yyyymmdd::parse("2025040").is_err());
+
+        let time = NaiveTime::from_hms_opt(9, 31, 0).unwrap();
+        assert_eq!(Hhmmss::parse("09:31:00").unwrap(), time);
+        assert_eq!(Hhmmss::parse("093100").unwrap(), time);
+
+        let datetime = date.and_time(time);
+        assert_eq!(Datetime::parse("2025-04-07 09:31:00").unwrap(), datetime);
+        let shanghai = Shanghai::parse("2025-04-07 09:31:00").unwrap();
+        assert_eq!(shanghai.naive_utc(), datetime - chrono::Duration::hours(8));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 7).unwrap();
+        assert_eq!(
+            deserialize::<Yyyymmdd, _>(&json!("20250407")).unwrap(),
+            date
+        );
+        assert_eq!(deserialize::<Yyyymmdd, _>(&json!(20250407)).unwrap(), date);
+        assert!(deserialize::<Yyyymmdd, _>(&json!(null)).is_err());
+        assert!(deserialize::<Yyyymmdd, _>(&json!([])).is_err());
+
+        assert_eq!(deserialize_option::<Hhmmss, _>(&json!("")).unwrap(), None);
+        assert_eq!(deserialize_option::<Hhmmss, _>(&json!(null)).unwrap(), None);
+        assert!(deserialize_option::<Hhmmss, _>(&json!("9 o'clock")).is_err());
+
+        let cell = |json: &str| {
+            deserialize_or_default::<Yyyymmdd, _>(&mut serde_json::Deserializer::from_str(json))
+                .unwrap()
+        };
+        assert_eq!(cell("20250407"), date);
+        assert_eq!(cell("\"20250407\""), date);
+        assert_eq!(cell("\"\""), NaiveDate::default());
+        assert_eq!(cell("\"today\""), NaiveDate::default());
+        assert_eq!(cell("[1, {}]"), NaiveDate::default());
+    }
+
+    #[test]
+    fn test_date_param() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 7).unwrap();
+        assert_eq!(date.to_param(), json!("20250407"));
+        assert_eq!(Some(date).to_param(), json!("20250407"));
+        assert_eq!(None::<NaiveDate>.to_param(), Value::Null);
+    }
+}
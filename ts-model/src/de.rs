@@ -0,0 +1,667 @@
+//! Single-pass deserialization of Tushare response bodies
+//!
+//! [`RowPage::decode`] reads the body bytes straight into typed rows: `fields`
+//! is resolved once, then every cell of `items` is deserialized into its struct
+//! field without building an intermediate `serde_json::Value` tree.
+
+use std::{borrow::Cow, cell::RefCell, fmt, marker::PhantomData};
+
+use serde::{
+    de::{
+        self, value, DeserializeSeed, Deserializer, IgnoredAny, IntoDeserializer, MapAccess,
+        SeqAccess, Visitor,
+    },
+    Deserialize,
+};
+use serde_json::Value;
+
+use crate::{PageBody, TsError, TsRow};
+
+/// The typed rows of one response page
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowPage<T> {
+    /// Rows parsed from `data.items`
+    pub rows: Vec<T>,
+    /// `data.has_more`, if the response reported it
+    pub has_more: Option<bool>,
+}
+
+impl<T: TsRow> RowPage<T> {
+    /// Decode a response body of the model's own API, checking its `code` like
+    /// [`TsError::check_code`]
+    pub fn decode(body: &[u8]) -> Result<Self, TsError> {
+        Self::decode_for(T::api_name(), body)
+    }
+
+    /// Decode a response body of `api_name`, whose rows are parsed into `T`
+    ///
+    /// Endpoints may reuse the model of another API, so errors are reported for
+    /// the API that was actually called rather than [`TsRow::api_name`].
+    pub fn decode_for(api_name: &str, body: &[u8]) -> Result<Self, TsError> {
+        Self::decode_body(api_name, body).map_err(|e| e.with_api_name(api_name))
+    }
+
+    fn decode_body(api_name: &str, body: &[u8]) -> Result<Self, TsError> {
+        let ctx = Context {
+            api_name: api_name.to_string(),
+            field_names: T::field_names(),
+            error: RefCell::new(None),
+        };
+
+        let mut deserializer = serde_json::Deserializer::from_slice(body);
+        let result = BodySeed::<T> {
+            ctx: &ctx,
+            marker: PhantomData,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|body| deserializer.end().map(|_| body));
+
+        let body = match result {
+            Ok(body) => body,
+            // Prefer the error recorded while parsing a row over serde's message
+            Err(e) => {
+                return Err(ctx
+                    .error
+                    .into_inner()
+                    .unwrap_or_else(|| TsError::response(api_name, e.to_string())))
+            }
+        };
+
+        let mut envelope = serde_json::Map::new();
+        envelope.insert("code".to_string(), body.code);
+        envelope.insert("msg".to_string(), body.msg);
+        TsError::check_code(api_name, &Value::Object(envelope))?;
+
+        body.data
+            .ok_or_else(|| TsError::response(api_name, "Missing 'data' field in response"))?
+    }
+}
+
+impl<T: TsRow + Send + 'static> PageBody for RowPage<T> {
+    fn decode(api_name: &str, body: &[u8]) -> Result<Self, TsError> {
+        RowPage::decode_for(api_name, body)
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn has_more(&self, page_size: usize) -> bool {
+        self.has_more.unwrap_or(self.rows.len() >= page_size)
+    }
+}
+
+/// State shared by the nested seeds of one decode
+struct Context {
+    api_name: String,
+    field_names: Vec<&'static str>,
+    /// The first error that is not a plain JSON error, surfaced instead of serde's
+    error: RefCell<Option<TsError>>,
+}
+
+impl Context {
+    fn fail<E: de::Error>(&self, err: TsError) -> E {
+        let msg = err.to_string();
+        self.error.borrow_mut().get_or_insert(err);
+        E::custom(msg)
+    }
+}
+
+/// What the top-level object of a response body holds
+struct Body<T> {
+    code: Value,
+    msg: Value,
+    data: Option<Result<RowPage<T>, TsError>>,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum BodyKey {
+    Code,
+    Msg,
+    Data,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum DataKey {
+    Fields,
+    Items,
+    HasMore,
+    #[serde(other)]
+    Other,
+}
+
+struct BodySeed<'a, T> {
+    ctx: &'a Context,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: TsRow> DeserializeSeed<'de> for BodySeed<'_, T> {
+    type Value = Body<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: TsRow> Visitor<'de> for BodySeed<'_, T> {
+    type Value = Body<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Tushare response object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut body = Body {
+            code: Value::Null,
+            msg: Value::Null,
+            data: None,
+        };
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                BodyKey::Code => body.code = map.next_value()?,
+                BodyKey::Msg => body.msg = map.next_value()?,
+                BodyKey::Data => {
+                    body.data = map.next_value_seed(DataSeed::<T> {
+                        ctx: self.ctx,
+                        marker: PhantomData,
+                    })?
+                }
+                BodyKey::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(body)
+    }
+}
+
+/// Deserializes `data`, which is `null` when the call failed
+struct DataSeed<'a, T> {
+    ctx: &'a Context,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: TsRow> DeserializeSeed<'de> for DataSeed<'_, T> {
+    type Value = Option<Result<RowPage<T>, TsError>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de, T: TsRow> Visitor<'de> for DataSeed<'_, T> {
+    type Value = Option<Result<RowPage<T>, TsError>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a data object or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let api_name = self.ctx.api_name.as_str();
+        let mut columns: Option<Vec<Option<usize>>> = None;
+        let mut rows: Option<Vec<T>> = None;
+        // `items` listed before `fields` can only be parsed once the columns are known
+        let mut pending: Option<Vec<Vec<Value>>> = None;
+        let mut has_more = None;
+
+        while let Some(key) = map.next_key()? {
+            match key {
+                DataKey::Fields => {
+                    let names: Vec<Str<'de>> = map.next_value()?;
+                    let fields: Vec<&str> = names.iter().map(|name| name.0.as_ref()).collect();
+                    let resolved =
+                        T::resolve_columns(Some(&fields)).map_err(|e| self.ctx.fail(e))?;
+                    columns = Some(resolved);
+                }
+                DataKey::Items => match &columns {
+                    Some(columns) => {
+                        rows = Some(map.next_value_seed(ItemsSeed::<T> {
+                            ctx: self.ctx,
+                            columns,
+                            fields: field_of_column(columns),
+                            marker: PhantomData,
+                        })?)
+                    }
+                    None => pending = Some(map.next_value()?),
+                },
+                DataKey::HasMore => has_more = map.next_value()?,
+                DataKey::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let rows = match (rows, pending) {
+            (Some(rows), _) => rows,
+            (None, Some(pending)) => {
+                let columns = match columns {
+                    Some(columns) => columns,
+                    None => match T::resolve_columns(None) {
+                        Ok(columns) => columns,
+                        Err(e) => return Ok(Some(Err(e))),
+                    },
+                };
+                match pending
+                    .iter()
+                    .map(|row| T::from_row(&columns, row))
+                    .collect()
+                {
+                    Ok(rows) => rows,
+                    Err(e) => return Ok(Some(Err(e))),
+                }
+            }
+            (None, None) => {
+                return Ok(Some(Err(TsError::response(
+                    api_name,
+                    "Missing 'items' field in data",
+                ))))
+            }
+        };
+
+        Ok(Some(Ok(RowPage { rows, has_more })))
+    }
+}
+
+/// A column name, borrowed from the body unless it contains escapes
+#[derive(Deserialize)]
+struct Str<'a>(#[serde(borrow)] Cow<'a, str>);
+
+/// Invert the field → column positions into column → field
+fn field_of_column(columns: &[Option<usize>]) -> Vec<Option<usize>> {
+    let len = columns.iter().flatten().max().map_or(0, |max| max + 1);
+    let mut fields = vec![None; len];
+    for (field, column) in columns.iter().enumerate() {
+        if let Some(column) = column {
+            fields[*column] = Some(field);
+        }
+    }
+    fields
+}
+
+struct ItemsSeed<'a, T> {
+    ctx: &'a Context,
+    /// Column of every field, as returned by `TsRow::resolve_columns`
+    columns: &'a [Option<usize>],
+    /// Field of every column, `None` for columns the model does not read
+    fields: Vec<Option<usize>>,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: TsRow> DeserializeSeed<'de> for ItemsSeed<'_, T> {
+    type Value = Vec<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: TsRow> Visitor<'de> for ItemsSeed<'_, T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of items")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut rows = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(row) = seq.next_element_seed(RowSeed::<T> {
+            ctx: self.ctx,
+            columns: self.columns,
+            fields: &self.fields,
+            marker: PhantomData,
+        })? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+struct RowSeed<'a, T> {
+    ctx: &'a Context,
+    columns: &'a [Option<usize>],
+    fields: &'a [Option<usize>],
+    marker: PhantomData<T>,
+}
+
+impl<'de, T: TsRow> DeserializeSeed<'de> for RowSeed<'_, T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: TsRow> Visitor<'de> for RowSeed<'_, T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an item array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut builder = T::Builder::default();
+        let mut column = 0;
+
+        loop {
+            let field = self.fields.get(column).copied().flatten();
+            let cell = CellSeed::<T> {
+                ctx: self.ctx,
+                builder: &mut builder,
+                field,
+                column,
+            };
+            if seq.next_element_seed(cell)?.is_none() {
+                break;
+            }
+            column += 1;
+        }
+
+        T::build(builder, self.columns).map_err(|e| self.ctx.fail(e))
+    }
+}
+
+struct CellSeed<'a, T: TsRow> {
+    ctx: &'a Context,
+    builder: &'a mut T::Builder,
+    field: Option<usize>,
+    column: usize,
+}
+
+impl<'de, T: TsRow> DeserializeSeed<'de> for CellSeed<'_, T> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let Some(field) = self.field else {
+            return IgnoredAny::deserialize(deserializer).map(|_| ());
+        };
+
+        T::deserialize_cell(self.builder, field, deserializer).map_err(|e| {
+            let name = self.ctx.field_names.get(field).copied().unwrap_or_default();
+            self.ctx
+                .fail(TsError::parse(&self.ctx.api_name, name, self.column, e))
+        })
+    }
+}
+
+/// Deserialize a `#[serde(default)]` cell, falling back to the default
+///
+/// Like `serde_json::from_value(..).unwrap_or_default()`, `null` and cells of
+/// another type (e.g. a string in a number column) become `T::default()`
+/// instead of failing the whole response.
+pub fn deserialize_or_default<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + Default,
+    D: Deserializer<'de>,
+{
+    let value: Result<T, value::Error> = match Scalar::deserialize(deserializer)? {
+        Scalar::Bool(value) => T::deserialize(value.into_deserializer()),
+        Scalar::I64(value) => T::deserialize(value.into_deserializer()),
+        Scalar::U64(value) => T::deserialize(value.into_deserializer()),
+        Scalar::F64(value) => T::deserialize(value.into_deserializer()),
+        Scalar::Str(Cow::Borrowed(value)) => {
+            T::deserialize(value::BorrowedStrDeserializer::new(value))
+        }
+        Scalar::Str(Cow::Owned(value)) => T::deserialize(value.into_deserializer()),
+        Scalar::Null | Scalar::Other => return Ok(T::default()),
+    };
+    Ok(value.unwrap_or_default())
+}
+
+/// A cell read without knowing its type, strings borrowed from the body when possible
+pub(crate) enum Scalar<'de> {
+    Null,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(Cow<'de, str>),
+    /// An array or object, which Tushare does not put in cells
+    Other,
+}
+
+impl<'de> Deserialize<'de> for Scalar<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ScalarVisitor)
+    }
+}
+
+struct ScalarVisitor;
+
+impl<'de> Visitor<'de> for ScalarVisitor {
+    type Value = Scalar<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a cell")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Scalar::Bool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Scalar::I64(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Scalar::U64(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Scalar::F64(value))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+        Ok(Scalar::Str(Cow::Borrowed(value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Scalar::Str(Cow::Owned(value.to_string())))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Scalar::Str(Cow::Owned(value)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Scalar::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Scalar::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(Scalar::Other)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok(Scalar::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use ts_derive::TsResponse;
+
+    use crate::*;
+
+    /// Decode a body through both paths and check that they agree
+    fn decode_both<T>(body: &Value) -> Result<Vec<T>, TsError>
+    where
+        T: TsRow + std::fmt::Debug + PartialEq,
+    {
+        let bytes = serde_json::to_vec(body).unwrap();
+        let single_pass = RowPage::<T>::decode(&bytes).map(|page| page.rows);
+        let from_json = T::from_json(body);
+        match (&single_pass, &from_json) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b),
+            // serde errors of the single pass also carry the line and column
+            (Err(a), Err(b)) => {
+                assert_eq!(std::mem::discriminant(a), std::mem::discriminant(b))
+            }
+            _ => panic!("paths disagree: {:?} vs {:?}", single_pass, from_json),
+        }
+        single_pass
+    }
+
+    #[derive(TsResponse, Deserialize, Debug, PartialEq)]
+    #[response(api = "test")]
+    struct Row {
+        code: String,
+        #[serde(default)]
+        price: f64,
+        vol: Option<i64>,
+    }
+
+    #[test]
+    fn test_decode_matches_from_json() {
+        let body = json!({
+            "code": 0,
+            "msg": "",
+            "data": {
+                "fields": ["vol", "extra", "code", "price"],
+                "items": [
+                    [1, [1, 2], "a", 1.5],
+                    [null, {"x": 1}, "b", null],
+                    [3, null, "c", "not a number"]
+                ],
+                "has_more": true
+            }
+        });
+        let rows = decode_both::<Row>(&body).unwrap();
+        assert_eq!(
+            rows[0],
+            Row {
+                code: "a".into(),
+                price: 1.5,
+                vol: Some(1)
+            }
+        );
+        assert_eq!(
+            rows[1],
+            Row {
+                code: "b".into(),
+                price: 0.0,
+                vol: None
+            }
+        );
+        assert_eq!(rows[2].price, 0.0);
+
+        let page = RowPage::<Row>::decode(&serde_json::to_vec(&body).unwrap()).unwrap();
+        assert_eq!(page.has_more, Some(true));
+
+        // `items` before `fields`
+        let body = json!({"data": {"items": [["a", 2.0]], "fields": ["code", "price"]}});
+        let rows = decode_both::<Row>(&body).unwrap();
+        assert_eq!(
+            rows[0],
+            Row {
+                code: "a".into(),
+                price: 2.0,
+                vol: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        // Required field null or missing
+        let body = json!({"data": {"fields": ["code"], "items": [[null]]}});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::Parse {
+                field: "code",
+                index: 0,
+                ..
+            })
+        ));
+
+        let body = json!({"data": {"fields": ["price", "code"], "items": [[1.0]]}});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::Parse {
+                field: "code",
+                index: 1,
+                ..
+            })
+        ));
+
+        let body = json!({"data": {"fields": ["price"], "items": []}});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::MissingColumn { field: "code", .. })
+        ));
+
+        // Wrong type of an Option field
+        let body = json!({"data": {"fields": ["code", "vol"], "items": [["a", "x"]]}});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::Parse {
+                field: "vol",
+                index: 1,
+                ..
+            })
+        ));
+
+        // Business errors come with a null `data`
+        let body = json!({"code": 40203, "msg": "抱歉，您没有访问该接口的权限", "data": null});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::Permission { .. })
+        ));
+
+        let body = json!({"code": 0, "msg": ""});
+        assert!(matches!(
+            decode_both::<Row>(&body),
+            Err(TsError::Response { .. })
+        ));
+
+        assert!(RowPage::<Row>::decode(b"{\"data\": ").is_err());
+    }
+
+    #[test]
+    fn test_decode_for_other_api() {
+        // Errors name the API that was called, not the one the model belongs to
+        let decode = |body: Value| {
+            RowPage::<Row>::decode_for("other", &serde_json::to_vec(&body).unwrap()).unwrap_err()
+        };
+
+        let err = decode(json!({"data": {"fields": ["code", "vol"], "items": [["a", "x"]]}}));
+        assert!(matches!(err, TsError::Parse { .. }));
+        assert_eq!(err.api_name(), Some("other"));
+
+        let err = decode(json!({"data": {"fields": ["code"], "items": [[null]]}}));
+        assert!(matches!(err, TsError::Parse { .. }));
+        assert_eq!(err.api_name(), Some("other"));
+
+        let err = decode(json!({"data": {"fields": ["price"], "items": []}}));
+        assert!(matches!(err, TsError::MissingColumn { .. }));
+        assert_eq!(err.api_name(), Some("other"));
+
+        let err = decode(json!({"code": 40203, "msg": "抱歉，您每分钟最多访问该接口2次"}));
+        assert!(matches!(err, TsError::RateLimited { .. }));
+        assert_eq!(err.api_name(), Some("other"));
+    }
+}
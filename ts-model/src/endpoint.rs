@@ -1,4 +1,5 @@
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use ts_derive::TsEndpoint;
 
 use crate::{
@@ -6,135 +7,590 @@ use crate::{
     ThsHotItem, ThsMoneyflowCptItem, ThsMoneyflowItem,
 };
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "limit_step", desc = "获取每天连板个数晋级的股票", resp = LimitStepItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "limit_step",
+    desc = "获取每天连板个数晋级的股票",
+    resp = LimitStepItem,
+    skip_empty,
+    tool = "limit_step"
+)]
 pub struct LimitStepReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 开始日期(YYYYMMDD格式)
+    #[serde(default)]
     pub start_date: String,
+    /// 结束日期(YYYYMMDD格式)
+    #[serde(default)]
     pub end_date: String,
+    /// 连板个数,支持多个输入,例如nums='2,3'
+    #[serde(default)]
     pub nums: String,
 }
 
 #[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "limit_step", desc = "获取每天连板个数晋级的股票", resp = LimitStepItem)]
+#[endpoint(api = "limit_step", desc = "获取每天连板个数晋级的股票", resp = LimitStepItem, skip_empty)]
 pub struct HisLimitStepReq {
     pub start_date: String,
     pub end_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "ths_hot", desc = "获取同花顺App热榜数据", resp = ThsHotItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "ths_hot",
+    desc = "获取同花顺App热榜数据",
+    resp = ThsHotItem,
+    skip_empty,
+    tool = "ths_hot"
+)]
 pub struct ThsHotReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 热榜类型(默认: 热股)
+    #[serde(default = "default_ths_hot_market")]
     pub market: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "kpl_list", desc = "获取涨跌停板数据", resp = KplListItem)]
+fn default_ths_hot_market() -> String {
+    "热股".to_string()
+}
+
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "kpl_list",
+    desc = "获取涨跌停板数据",
+    resp = KplListItem,
+    skip_empty,
+    tool = "kpl_list"
+)]
 pub struct KplListReq {
+    /// 板单类型(枚举值: 涨停/炸板/跌停/自然涨停/竞价)
+    #[serde(default)]
     pub tag: String,
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
 }
 
 #[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "limit_list_ths", desc = "涨跌停榜单(同花顺)", resp = KplListItem)]
+#[endpoint(api = "limit_list_ths", desc = "涨跌停榜单(同花顺)", resp = KplListItem, skip_empty)]
 pub struct LimitListThs {
     pub tag: String,
     pub trade_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "kpl_concept", desc = "获取开盘啦概念题材列表", resp = ConceptListItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "kpl_concept",
+    desc = "获取开盘啦概念题材列表",
+    resp = ConceptListItem,
+    skip_empty,
+    tool = "kpl_concept"
+)]
 pub struct KplConceptReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "kpl_concept_cons", desc = "获取开盘啦概念题材的成分股", resp = KplConceptConsItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "kpl_concept_cons",
+    desc = "获取开盘啦概念题材的成分股",
+    resp = KplConceptConsItem,
+    skip_empty,
+    tool = "kpl_concept_cons"
+)]
 pub struct KplConceptConsReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 题材代码(xxxxxx.KP格式)
+    #[serde(default)]
     pub ts_code: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "limit_cpt_list", desc = "获取每天涨停股票最多最强的概念板块", resp = LimitCptListItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "limit_cpt_list",
+    desc = "获取每天涨停股票最多最强的概念板块",
+    resp = LimitCptListItem,
+    skip_empty,
+    tool = "limit_cpt_list"
+)]
 pub struct LimitCptListReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 开始日期(YYYYMMDD格式)
+    #[serde(default)]
     pub start_date: String,
+    /// 结束日期(YYYYMMDD格式)
+    #[serde(default)]
     pub end_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "moneyflow_ths", desc = "获取同花顺个股资金流向数据", resp = ThsMoneyflowItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "moneyflow_ths",
+    desc = "获取同花顺个股资金流向数据",
+    resp = ThsMoneyflowItem,
+    skip_empty,
+    tool = "moneyflow_ths"
+)]
 pub struct ThsMoneyflowReq {
+    /// 股票代码
+    #[serde(default)]
     pub ts_code: String,
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 开始日期(YYYYMMDD格式)
+    #[serde(default)]
     pub start_date: String,
+    /// 结束日期(YYYYMMDD格式)
+    #[serde(default)]
     pub end_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "moneyflow_cnt_ths", desc = "获取同花顺概念板块每日资金流向", resp = ThsMoneyflowCptItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "moneyflow_cnt_ths",
+    desc = "获取同花顺概念板块每日资金流向",
+    resp = ThsMoneyflowCptItem,
+    skip_empty,
+    tool = "moneyflow_cnt_ths"
+)]
 pub struct ThsMoneyflowCptReq {
+    /// 交易日期(YYYYMMDD格式)
+    #[serde(default)]
     pub trade_date: String,
+    /// 开始日期(YYYYMMDD格式)
+    #[serde(default)]
     pub start_date: String,
+    /// 结束日期(YYYYMMDD格式)
+    #[serde(default)]
     pub end_date: String,
 }
 
-#[derive(TsEndpoint, Debug, Serialize)]
-#[endpoint(api = "stk_mins", desc = "获取A股分钟数据", resp = StkMinsItem)]
+#[derive(TsEndpoint, Debug, Serialize, Deserialize, JsonSchema)]
+#[endpoint(
+    api = "stk_mins",
+    desc = "获取A股分钟数据",
+    resp = StkMinsItem,
+    page_size = 8000,
+    skip_empty,
+    tool = "stk_mins"
+)]
 pub struct StkMinsReq {
+    /// 股票代码
     pub ts_code: String,
+    /// 分钟频度(枚举值: 1min/5min/15min/30min/60min)
     pub freq: String,
+    /// 开始日期
     pub start_date: Option<String>,
+    /// 结束日期
     pub end_date: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+    use serde_json::{json, Value};
+
     use crate::endpoint::*;
+    use crate::model::ConceptListItemField;
+    use crate::{MockTransport, RetryPolicy, ToolError, TsClient, TsError, TsRow};
+
+    #[test]
+    fn test_params_skip_none_and_empty() {
+        let params = StkMinsReq {
+            ts_code: "600000.SH".to_string(),
+            freq: "1min".to_string(),
+            start_date: None,
+            end_date: Some("2025-04-07 15:00:00".to_string()),
+        }
+        .__params()
+        .unwrap();
+        assert_eq!(params.len(), 3);
+        assert!(!params.contains_key("start_date"));
+        assert_eq!(params["end_date"], "2025-04-07 15:00:00");
+
+        let params = LimitStepReq {
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
+            nums: "2,3".to_string(),
+        }
+        .__params()
+        .unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["trade_date"], "20250407");
+        assert_eq!(params["nums"], "2,3");
+    }
+
+    #[test]
+    fn test_params_dates() {
+        #[derive(TsEndpoint, Debug, Serialize)]
+        #[endpoint(api = "trade_cal", desc = "交易日历")]
+        struct TradeCalReq {
+            start_date: NaiveDate,
+            end_date: Option<NaiveDate>,
+        }
+
+        let params = TradeCalReq {
+            start_date: NaiveDate::from_ymd_opt(2025, 4, 7).unwrap(),
+            end_date: None,
+        }
+        .__params()
+        .unwrap();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params["start_date"], "20250407");
+    }
+
+    #[test]
+    fn test_with_fields() {
+        let request = KplConceptReq {
+            trade_date: "20250407".to_string(),
+        };
+        let requester =
+            request.with_fields([ConceptListItemField::TsCode, ConceptListItemField::ZTNum]);
+        assert_eq!(requester.fields, Some(vec!["ts_code", "z_t_num"]));
+
+        let requester = requester.with_field_names(vec!["name"]);
+        assert_eq!(requester.fields, Some(vec!["name"]));
+    }
+
+    /// A response body from `ts-model/fixtures`
+    fn fixture(name: &str) -> Value {
+        let path = format!("{}/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn mock_client(transport: &MockTransport) -> TsClient {
+        TsClient::builder()
+            .transport(transport.clone())
+            .rate_limit(None)
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y%m%d").unwrap()
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
 
     #[tokio::test]
-    async fn test() {
-        let res = ThsHotReq {
+    async fn test_ths_hot() {
+        let transport = MockTransport::new().with_response(
+            "ths_hot",
+            json!({"trade_date": "20250407", "market": "热股"}),
+            fixture("ths_hot"),
+        );
+        let client = mock_client(&transport);
+        let request = || ThsHotReq {
             trade_date: "20250407".to_string(),
             market: "热股".to_string(),
+        };
+
+        let res = request().execute_with(&client).await.unwrap();
+        assert_eq!(res["data"]["items"].as_array().unwrap().len(), 2);
+
+        let res = request().execute_typed_with(&client).await.unwrap();
+        assert_eq!(res[0].trade_date, date("20250407"));
+        assert_eq!(res[0].ts_code, "002261.SZ");
+        assert_eq!(res[0].rank, 1);
+        assert_eq!(res[1].ts_name, "实达集团");
+        assert_eq!(res[1].hot, 318764.0);
+        assert_eq!(res[1].rank_time, "2025-04-07 22:30:00");
+
+        // The typed call requests every column of the model
+        let requests = transport.requests();
+        assert_eq!(
+            requests[1].fields,
+            Some(strings(&ThsHotItem::field_names()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kpl_list() {
+        let transport = MockTransport::new().with_response(
+            "kpl_list",
+            json!({"tag": "涨停", "trade_date": "20250407"}),
+            fixture("kpl_list"),
+        );
+        let res = KplListReq {
+            tag: "涨停".to_string(),
+            trade_date: "20250407".to_string(),
         }
-        .execute()
+        .execute_typed_with(&mock_client(&transport))
         .await
-        .unwrap_or_default();
+        .unwrap();
 
-        println!("res: {:?}", res);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].name, "实达集团");
+        assert_eq!(res[0].lu_time, NaiveTime::from_hms_opt(9, 25, 0));
+        assert_eq!(res[0].open_time, None);
+        assert_eq!(res[0].free_float, 901660000.0);
+        assert_eq!(res[1].status, "2连板");
+        assert_eq!(res[1].open_time, NaiveTime::from_hms_opt(10, 31, 5));
+        assert_eq!(res[1].net_change, -120354000.0);
     }
 
     #[tokio::test]
-    async fn test2() {
-        let res: Vec<KplListItem> = KplListReq {
-            tag: "涨停".to_string(),
+    async fn test_kpl_concept() {
+        let transport = MockTransport::new().with_response(
+            "kpl_concept",
+            json!({"trade_date": "20250407"}),
+            fixture("kpl_concept"),
+        );
+        let client = mock_client(&transport);
+        let request = || KplConceptReq {
             trade_date: "20250407".to_string(),
+        };
+
+        let res = request().execute_typed_with(&client).await.unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].trade_date, date("20250407"));
+        assert_eq!(res[0].name, "华为鸿蒙");
+        assert_eq!(res[0].z_t_num, 12);
+        assert_eq!(res[1].up_num, "-1");
+
+        // A partial selection requests only the chosen columns
+        let res = request()
+            .with_fields([ConceptListItemField::TsCode, ConceptListItemField::ZTNum])
+            .execute_partial_with(&client)
+            .await
+            .unwrap();
+        assert_eq!(res[2].ts_code.as_deref(), Some("000017.KP"));
+        assert_eq!(res[2].z_t_num, Some(5));
+        let requests = transport.requests();
+        assert_eq!(
+            requests[1].fields,
+            Some(vec!["ts_code".to_string(), "z_t_num".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kpl_concept_cons() {
+        let transport = MockTransport::new().with_response(
+            "kpl_concept_cons",
+            json!({"trade_date": "20250407", "ts_code": "000111.KP"}),
+            fixture("kpl_concept_cons"),
+        );
+        let res = KplConceptConsReq {
+            trade_date: "20250407".to_string(),
+            ts_code: "000111.KP".to_string(),
         }
-        .execute_typed()
+        .execute_typed_with(&mock_client(&transport))
         .await
-        .unwrap_or_default();
+        .unwrap();
 
-        println!("{:?}", res);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].con_code, "002261.SZ");
+        assert_eq!(res[0].hot_num, "58742");
+        assert_eq!(res[1].con_name, "润和软件");
+        assert_eq!(res[1].trade_date, date("20250407"));
+        // A null in a #[serde(default)] field is defaulted
+        assert_eq!(res[1].hot_num, "");
     }
 
     #[tokio::test]
-    async fn test_his_limit_step() {
+    async fn test_limit_step() {
+        let transport = MockTransport::new()
+            .with_response(
+                "limit_step",
+                json!({"trade_date": "20250407", "nums": "2,3"}),
+                fixture("limit_step"),
+            )
+            .with_response(
+                "limit_step",
+                json!({"start_date": "20250407", "end_date": "20250409"}),
+                fixture("limit_step_history"),
+            );
+        let client = mock_client(&transport);
+
+        let res = LimitStepReq {
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
+            nums: "2,3".to_string(),
+        }
+        .execute_typed_with(&client)
+        .await
+        .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[1].ts_code, "603388.SH");
+        assert_eq!(res[1].nums, "3");
+
         let res = HisLimitStepReq {
             start_date: "20250407".to_string(),
             end_date: "20250409".to_string(),
         }
-        .execute_typed()
+        .execute_typed_with(&client)
         .await
-        .unwrap_or_default();
+        .unwrap();
+        let nums: Vec<_> = res.iter().map(|item| item.nums.as_str()).collect();
+        assert_eq!(nums, ["5", "4", "3"]);
+        assert_eq!(res[0].trade_date, date("20250409"));
+    }
 
-        for item in res {
-            println!(
-                "code: {:?} name: {:?}, status: {:?} trade_date: {:?}",
-                item.ts_code, item.name, item.nums, item.trade_date
-            );
+    #[tokio::test]
+    async fn test_limit_cpt_list() {
+        let transport = MockTransport::new().with_response(
+            "limit_cpt_list",
+            json!({"trade_date": "20250407"}),
+            fixture("limit_cpt_list"),
+        );
+        let res = LimitCptListReq {
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
         }
+        .execute_typed_with(&mock_client(&transport))
+        .await
+        .unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].name, "华为鸿蒙");
+        assert_eq!(res[0].up_stat, "2天2板");
+        assert_eq!(res[0].up_nums, 12);
+        assert_eq!(res[1].pct_chg, 2.11);
+        assert_eq!(res[1].rank, "2");
+    }
+
+    #[tokio::test]
+    async fn test_moneyflow_ths() {
+        let transport = MockTransport::new().with_response(
+            "moneyflow_ths",
+            json!({"ts_code": "002261.SZ", "trade_date": "20250407"}),
+            fixture("moneyflow_ths"),
+        );
+        let res = ThsMoneyflowReq {
+            ts_code: "002261.SZ".to_string(),
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
+        }
+        .execute_typed_with(&mock_client(&transport))
+        .await
+        .unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].name, "拓维信息");
+        assert_eq!(res[0].net_amount, -12035.4);
+        assert_eq!(res[0].buy_lg_amount_rate, -2.16);
+        assert_eq!(res[0].buy_sm_amount, -1479.7);
+    }
+
+    #[tokio::test]
+    async fn test_moneyflow_cnt_ths() {
+        let transport = MockTransport::new().with_response(
+            "moneyflow_cnt_ths",
+            json!({"trade_date": "20250407"}),
+            fixture("moneyflow_cnt_ths"),
+        );
+        let res = ThsMoneyflowCptReq {
+            trade_date: "20250407".to_string(),
+            start_date: String::new(),
+            end_date: String::new(),
+        }
+        .execute_typed_with(&mock_client(&transport))
+        .await
+        .unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].lead_stock, "拓维信息");
+        assert_eq!(res[0].company_num, 102);
+        assert_eq!(res[1].ts_code, "885694.TI");
+        assert_eq!(res[1].net_amount, -185000000.0);
+    }
+
+    #[tokio::test]
+    async fn test_stk_mins() {
+        let transport = MockTransport::new().with_response(
+            "stk_mins",
+            json!({"ts_code": "600000.SH", "freq": "1min"}),
+            fixture("stk_mins"),
+        );
+        let res = StkMinsReq {
+            ts_code: "600000.SH".to_string(),
+            freq: "1min".to_string(),
+            start_date: None,
+            end_date: None,
+        }
+        .execute_typed_all_with(&mock_client(&transport))
+        .await
+        .unwrap();
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(
+            res[0].trade_time,
+            date("20250407").and_hms_opt(9, 31, 0).unwrap()
+        );
+        assert_eq!(res[2].close, 10.1);
+        assert_eq!(res[1].vol, 842100);
+
+        // Paged calls add offset and limit to the params
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].params["offset"], 0);
+        assert_eq!(requests[0].params["limit"], 8000);
+    }
+
+    #[tokio::test]
+    async fn test_errors_name_the_called_api() {
+        // `limit_list_ths` parses its rows into the `kpl_list` model
+        let transport = MockTransport::new().with_response(
+            "limit_list_ths",
+            json!({"trade_date": "20250407"}),
+            json!({"code": 0, "data": {"fields": ["trade_date"], "items": [["yesterday"]]}}),
+        );
+        let request = || LimitListThs {
+            tag: String::new(),
+            trade_date: "20250407".to_string(),
+        };
+        let err = request()
+            .execute_typed_with(&mock_client(&transport))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TsError::Parse { .. }));
+        assert_eq!(err.api_name(), Some("limit_list_ths"));
+
+        transport.insert(
+            "limit_list_ths",
+            json!({"trade_date": "20250407"}),
+            json!({"code": 40203, "msg": "抱歉，您没有访问该接口的权限", "data": null}),
+        );
+        let err = request()
+            .execute_typed_with(&mock_client(&transport))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TsError::Permission { .. }));
+        assert_eq!(err.api_name(), Some("limit_list_ths"));
+        assert_eq!(
+            ToolError::from(err).report().api_name.as_deref(),
+            Some("limit_list_ths")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_request() {
+        let transport = MockTransport::new();
+        let err = KplConceptReq {
+            trade_date: "20250407".to_string(),
+        }
+        .execute_typed_with(&mock_client(&transport))
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, TsError::Unmatched { .. }));
     }
 }
@@ -0,0 +1,375 @@
+use thiserror::Error;
+
+/// Errors produced while calling a Tushare API and parsing its response
+#[derive(Debug, Error)]
+pub enum TsError {
+    /// `TUSHARE_TOKEN` is not available in the environment
+    #[error("TUSHARE_TOKEN environment variable not set")]
+    MissingToken,
+
+    /// The underlying HTTP client could not be built
+    #[error("failed to build HTTP client: {0}")]
+    Client(#[source] reqwest::Error),
+
+    /// The request params could not be serialized to JSON
+    #[error("failed to serialize params for '{api_name}': {source}")]
+    Params {
+        api_name: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The HTTP request could not be sent or its body could not be read
+    #[error("request to '{api_name}' failed: {source}")]
+    Http {
+        api_name: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// Tushare answered with a non-2xx HTTP status
+    #[error("request to '{api_name}' failed with status: {status}")]
+    Status {
+        api_name: String,
+        status: reqwest::StatusCode,
+    },
+
+    /// Tushare rejected the call with a non-zero `code`
+    #[error("'{api_name}' returned code {code}: {msg}")]
+    Api {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The token has no permission to call the API
+    #[error("no permission to call '{api_name}' (code {code}): {msg}")]
+    Permission {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The per-minute or per-hour call limit of the API was exceeded
+    #[error("rate limit exceeded for '{api_name}' (code {code}): {msg}")]
+    RateLimited {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The daily quota of the API is used up until the next day
+    #[error("daily quota exhausted for '{api_name}' (code {code}): {msg}")]
+    QuotaExhausted {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The account does not have enough points for the API
+    #[error("insufficient points to call '{api_name}' (code {code}): {msg}")]
+    InsufficientPoints {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The token was rejected by Tushare
+    #[error("invalid token for '{api_name}' (code {code}): {msg}")]
+    InvalidToken {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// Tushare rejected the request params
+    #[error("invalid params for '{api_name}' (code {code}): {msg}")]
+    InvalidParams {
+        api_name: String,
+        code: i64,
+        msg: String,
+    },
+
+    /// The response envelope is not shaped like `{"data": {"fields": [..], "items": [..]}}`
+    #[error("invalid response from '{api_name}': {reason}")]
+    Response { api_name: String, reason: String },
+
+    /// A column required by the model is not listed in `data.fields`
+    #[error("'{api_name}' response has no column '{field}'")]
+    MissingColumn {
+        api_name: String,
+        field: &'static str,
+    },
+
+    /// A paged query would return more rows than allowed
+    #[error("'{api_name}' returned more than {max_rows} rows")]
+    TooManyRows { api_name: String, max_rows: usize },
+
+    /// No canned response matches the request (see `MockTransport`)
+    #[error("no response for '{api_name}' with params {params}")]
+    Unmatched { api_name: String, params: String },
+
+    /// A cassette file could not be read or written
+    #[error("cassette '{path}': {reason}")]
+    Cassette { path: String, reason: String },
+
+    /// A single cell of a row could not be converted into the model field
+    #[error("failed to parse field '{field}' at index {index} of '{api_name}': {reason}")]
+    Parse {
+        api_name: String,
+        field: &'static str,
+        index: usize,
+        reason: String,
+    },
+}
+
+/// Codes Tushare uses for an invalid or expired token
+const INVALID_TOKEN_CODES: &[i64] = &[40101];
+/// Codes Tushare uses for permission, points and quota errors
+const PERMISSION_CODES: &[i64] = &[40203, 2002];
+/// Codes Tushare uses for missing or malformed params
+const INVALID_PARAMS_CODES: &[i64] = &[-2001, 40001];
+
+impl TsError {
+    /// Classify a non-zero Tushare `code`/`msg` pair
+    ///
+    /// The same code is shared by several failures (40203 covers both quota and
+    /// permission errors), so the message is matched first and the code is only
+    /// used as a fallback. Permission refusals often name the points an API
+    /// needs, so only a message saying the points are insufficient counts as
+    /// [`TsError::InsufficientPoints`].
+    pub fn from_code(api_name: &str, code: i64, msg: impl Into<String>) -> Self {
+        let api_name = api_name.to_string();
+        let msg = msg.into();
+
+        if msg.contains("每分钟") || msg.contains("每小时") {
+            TsError::RateLimited {
+                api_name,
+                code,
+                msg,
+            }
+        } else if msg.contains("每天最多") {
+            TsError::QuotaExhausted {
+                api_name,
+                code,
+                msg,
+            }
+        } else if msg.contains("积分") && msg.contains("不足") {
+            TsError::InsufficientPoints {
+                api_name,
+                code,
+                msg,
+            }
+        } else if msg.contains("token") || INVALID_TOKEN_CODES.contains(&code) {
+            TsError::InvalidToken {
+                api_name,
+                code,
+                msg,
+            }
+        } else if msg.contains("权限") || PERMISSION_CODES.contains(&code) {
+            TsError::Permission {
+                api_name,
+                code,
+                msg,
+            }
+        } else if msg.contains("参数") || INVALID_PARAMS_CODES.contains(&code) {
+            TsError::InvalidParams {
+                api_name,
+                code,
+                msg,
+            }
+        } else {
+            TsError::Api {
+                api_name,
+                code,
+                msg,
+            }
+        }
+    }
+
+    /// Check the `code` of a raw response body, turning a non-zero value into an error
+    ///
+    /// A body without `code` is accepted so that hand-built fixtures keep working.
+    pub fn check_code(api_name: &str, json: &serde_json::Value) -> Result<(), TsError> {
+        let code = match json.get("code") {
+            None | Some(serde_json::Value::Null) => return Ok(()),
+            Some(code) => code
+                .as_i64()
+                .or_else(|| code.as_str().and_then(|code| code.parse().ok()))
+                .ok_or_else(|| TsError::response(api_name, "'code' is not an integer"))?,
+        };
+
+        if code == 0 {
+            return Ok(());
+        }
+
+        let msg = json
+            .get("msg")
+            .and_then(|msg| msg.as_str())
+            .unwrap_or_default();
+        Err(TsError::from_code(api_name, code, msg))
+    }
+
+    /// Build a [`TsError::Response`] for a malformed response envelope
+    pub fn response(api_name: &str, reason: impl Into<String>) -> Self {
+        TsError::Response {
+            api_name: api_name.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Build a [`TsError::Parse`] for a cell that failed to convert
+    pub fn parse(api_name: &str, field: &'static str, index: usize, reason: impl ToString) -> Self {
+        TsError::Parse {
+            api_name: api_name.to_string(),
+            field,
+            index,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The Tushare API name the error belongs to, if any
+    pub fn api_name(&self) -> Option<&str> {
+        match self {
+            TsError::MissingToken | TsError::Client(_) | TsError::Cassette { .. } => None,
+            TsError::Params { api_name, .. }
+            | TsError::Http { api_name, .. }
+            | TsError::Status { api_name, .. }
+            | TsError::Api { api_name, .. }
+            | TsError::Permission { api_name, .. }
+            | TsError::RateLimited { api_name, .. }
+            | TsError::QuotaExhausted { api_name, .. }
+            | TsError::InsufficientPoints { api_name, .. }
+            | TsError::InvalidToken { api_name, .. }
+            | TsError::InvalidParams { api_name, .. }
+            | TsError::Response { api_name, .. }
+            | TsError::MissingColumn { api_name, .. }
+            | TsError::TooManyRows { api_name, .. }
+            | TsError::Unmatched { api_name, .. }
+            | TsError::Parse { api_name, .. } => Some(api_name),
+        }
+    }
+
+    /// Attribute the error to `api_name`, e.g. when it was raised by a model
+    /// shared with another API
+    pub fn with_api_name(mut self, api_name: &str) -> Self {
+        match &mut self {
+            TsError::MissingToken | TsError::Client(_) | TsError::Cassette { .. } => {}
+            TsError::Params { api_name: name, .. }
+            | TsError::Http { api_name: name, .. }
+            | TsError::Status { api_name: name, .. }
+            | TsError::Api { api_name: name, .. }
+            | TsError::Permission { api_name: name, .. }
+            | TsError::RateLimited { api_name: name, .. }
+            | TsError::QuotaExhausted { api_name: name, .. }
+            | TsError::InsufficientPoints { api_name: name, .. }
+            | TsError::InvalidToken { api_name: name, .. }
+            | TsError::InvalidParams { api_name: name, .. }
+            | TsError::Response { api_name: name, .. }
+            | TsError::MissingColumn { api_name: name, .. }
+            | TsError::TooManyRows { api_name: name, .. }
+            | TsError::Unmatched { api_name: name, .. }
+            | TsError::Parse { api_name: name, .. } => {
+                if name != api_name {
+                    *name = api_name.to_string();
+                }
+            }
+        }
+        self
+    }
+
+    /// Whether the failure is transient and the call may succeed when retried
+    ///
+    /// Timeouts, connection failures, 5xx/429 statuses and rate limits are
+    /// retryable; permission, token, points, parameter and daily quota errors
+    /// never are.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TsError::Http { source, .. } => {
+                source.is_timeout()
+                    || source.is_connect()
+                    || source.is_request()
+                    || source.is_body()
+            }
+            TsError::Status { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            TsError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// The Tushare business `code`, when the error came from the response body
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            TsError::Api { code, .. }
+            | TsError::Permission { code, .. }
+            | TsError::RateLimited { code, .. }
+            | TsError::QuotaExhausted { code, .. }
+            | TsError::InsufficientPoints { code, .. }
+            | TsError::InvalidToken { code, .. }
+            | TsError::InvalidParams { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_check_code() {
+        assert!(
+            TsError::check_code("kpl_list", &json!({"code": 0, "msg": "", "data": {}})).is_ok()
+        );
+        assert!(TsError::check_code("kpl_list", &json!({"data": {}})).is_ok());
+
+        let err = TsError::check_code(
+            "kpl_list",
+            &json!({"code": 40203, "msg": "抱歉，您每分钟最多访问该接口2次", "data": null}),
+        )
+        .unwrap_err();
+        assert!(matches!(err, TsError::RateLimited { code: 40203, .. }));
+        assert_eq!(err.api_name(), Some("kpl_list"));
+    }
+
+    #[test]
+    fn test_from_code() {
+        let classify = |code, msg| TsError::from_code("stk_mins", code, msg);
+
+        assert!(matches!(
+            classify(40203, "抱歉，您没有访问该接口的权限"),
+            TsError::Permission { .. }
+        ));
+        assert!(matches!(
+            classify(40203, "抱歉，您的积分不足"),
+            TsError::InsufficientPoints { .. }
+        ));
+        assert!(matches!(
+            classify(40203, "抱歉，您没有该接口权限，需要2000积分"),
+            TsError::Permission { .. }
+        ));
+        assert!(matches!(
+            classify(40203, "抱歉，您每小时最多访问该接口20次"),
+            TsError::RateLimited { .. }
+        ));
+        let quota = classify(40203, "抱歉，您每天最多访问该接口10000次");
+        assert!(matches!(quota, TsError::QuotaExhausted { code: 40203, .. }));
+        assert!(!quota.is_retryable());
+        assert!(matches!(
+            classify(40101, "您的token不对，请确认。"),
+            TsError::InvalidToken { .. }
+        ));
+        assert!(matches!(
+            classify(-2001, "参数错误"),
+            TsError::InvalidParams { .. }
+        ));
+        assert!(matches!(
+            classify(-1, "系统内部错误"),
+            TsError::Api { code: -1, .. }
+        ));
+    }
+}
@@ -1,5 +1,41 @@
+// Allow the derive output to refer to `::ts_model` from inside this crate too
+extern crate self as ts_model;
+
+pub mod api;
+pub mod cassette;
+pub mod client;
+pub mod convert;
+pub mod de;
 pub mod endpoint;
+pub mod error;
 pub mod model;
+pub mod page;
+pub mod query;
+pub mod rate_limit;
+pub mod registry;
+pub mod retry;
+pub mod row;
+pub mod stream;
+pub mod tool;
+pub mod transport;
+
+// Re-exported for the derive output, which registers endpoints, responses and tools
+#[doc(hidden)]
+pub use inventory;
 
+pub use api::TsApi;
+pub use cassette::{CassetteMode, CassetteTransport};
+pub use client::*;
+pub use de::RowPage;
 pub use endpoint::*;
+pub use error::*;
 pub use model::*;
+pub use page::*;
+pub use query::QueryPolicy;
+pub use rate_limit::*;
+pub use registry::{endpoints, responses, EndpointInfo, FieldInfo, ParamInfo, ResponseInfo};
+pub use retry::*;
+pub use row::{TsField, TsRow};
+pub use stream::TsStream;
+pub use tool::{find_tool, tools, ToolError, ToolErrorReport, TsTool};
+pub use transport::{HttpTransport, MockTransport, Transport, TsRequest};
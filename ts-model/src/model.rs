@@ -1,258 +1,518 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
 use ts_derive::TsResponse;
 
 #[derive(TsResponse, Serialize, Deserialize, Debug, Default)]
-#[response(api = "kpl_list")]
+#[response(api = "kpl_list", default_all)]
+#[serde(default)]
 pub struct KplListItem {
-    #[ts_field(0)]
-    #[serde(default)] 
+    /// 代码
     pub ts_code: String,
-    #[ts_field(1)]
-    #[serde(default)] 
+    /// 名称
     pub name: String,
-    #[ts_field(2)]
-    #[serde(default)] 
-    pub trade_date: String,
-    #[ts_field(3)]
-    #[serde(default)]
-    pub lu_time: String,
-    #[ts_field(4)]
-    #[serde(default)]
-    pub ld_time: String,
-    #[ts_field(5)]
-    #[serde(default)]
-    pub open_time: String,
-    #[ts_field(6)]
-    #[serde(default)]
-    pub last_time: String,
-    #[ts_field(7)]
-    #[serde(default)]
+    /// 交易时间
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: Option<NaiveDate>,
+    /// 涨停时间
+    #[ts_field(with = "hhmmss")]
+    pub lu_time: Option<NaiveTime>,
+    /// 跌停时间
+    #[ts_field(with = "hhmmss")]
+    pub ld_time: Option<NaiveTime>,
+    /// 开板时间
+    #[ts_field(with = "hhmmss")]
+    pub open_time: Option<NaiveTime>,
+    /// 最后涨停时间
+    #[ts_field(with = "hhmmss")]
+    pub last_time: Option<NaiveTime>,
+    /// 涨停原因
     pub lu_desc: String,
-    #[ts_field(8)]
-    #[serde(default)]
+    /// 标签
     pub tag: String,
-    #[ts_field(9)]
-    #[serde(default)]
+    /// 板块
     pub theme: String,
-    #[ts_field(10)]
-    #[serde(default)]
+    /// 主力净额(元)
     pub net_change: f64,
-    #[ts_field(11)]
-    #[serde(default)]
+    /// 竞价成交额(元)
     pub bid_amount: f64,
-    #[ts_field(12)]
-    #[serde(default)]
+    /// 状态（N连板）
     pub status: String,
-    #[ts_field(13)]
-    #[serde(default)]
+    /// 竞价净额
     pub bid_change: f64,
-    #[ts_field(14)]
-    #[serde(default)]
+    /// 竞价换手%
     pub bid_turnover: f64,
-    #[ts_field(15)]
-    #[serde(default)]
+    /// 涨停委买额
     pub lu_bid_vol: f64,
-    #[ts_field(16)]
-    #[serde(default)]
+    /// 涨跌幅%
     pub pct_chg: f64,
-    #[ts_field(17)]
-    #[serde(default)]
+    /// 竞价涨幅%
     pub bid_pct_chg: f64,
-    #[ts_field(18)]
-    #[serde(default)]
+    /// 实时涨幅%
     pub rt_pct_chg: f64,
-    #[ts_field(19)]
-    #[serde(default)]
+    /// 封单
     pub limit_order: f64,
-    #[ts_field(20)]
-    #[serde(default)]
+    /// 成交额
     pub amount: f64,
-    #[ts_field(21)]
-    #[serde(default)]
+    /// 换手率%
     pub turnover_rate: f64,
-    #[ts_field(22)]
-    #[serde(default)]
+    /// 实际流通
     pub free_float: f64,
-    #[ts_field(23)]
-    #[serde(default)]
+    /// 最大封单
     pub lu_limit_order: f64,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "kpl_concept")]
+#[response(api = "kpl_concept", partial_derive(Debug, Serialize))]
 pub struct ConceptListItem {
-    #[ts_field(0)]
-    pub trade_date: String,
-    #[ts_field(1)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 题材代码
     pub ts_code: String,
-    #[ts_field(2)]
+    /// 题材名称
     pub name: String,
-    #[ts_field(3)]
+    /// 涨停数量
     pub z_t_num: i64,
-    #[ts_field(4)]
+    /// 排名上升位数
     pub up_num: String,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "kpl_concept_cons")]
+#[response(api = "kpl_concept_cons", partial_derive(Debug, Serialize))]
 pub struct KplConceptConsItem {
-    #[ts_field(0)]
+    /// 题材ID
     pub ts_code: String,
-    #[ts_field(1)]
+    /// 题材名称
     pub name: String,
-    #[ts_field(2)]
+    /// 股票名称
     pub con_name: String,
-    #[ts_field(3)]
+    /// 股票代码
     pub con_code: String,
-    #[ts_field(4)]
-    pub trade_date: String,
-    #[ts_field(5)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 描述
     pub desc: String,
-    #[ts_field(6)]
+    /// 人气值
     #[serde(default)]
     pub hot_num: String,
 }
 
 #[derive(TsResponse, Serialize, Deserialize, Debug)]
-#[response(api = "ths_hot")]
+#[response(api = "ths_hot", partial_derive(Debug, Serialize))]
 pub struct ThsHotItem {
-    #[ts_field(0)]
-    pub trade_date: String,
-    #[ts_field(1)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 数据类型
     pub data_type: String,
-    #[ts_field(2)]
+    /// 股票代码
     pub ts_code: String,
-    #[ts_field(3)]
+    /// 股票名称
     pub ts_name: String,
-    #[ts_field(4)]
+    /// 排行
     pub rank: i32,
-    #[ts_field(5)]
+    /// 涨跌幅%
     pub pct_change: f64,
-    #[ts_field(6)]
+    /// 当前价格
     pub current_price: f64,
-    #[ts_field(7)]
+    /// 标签
     pub concept: String,
-    #[ts_field(8)]
+    /// 上榜解读
     pub rank_reason: String,
-    #[ts_field(9)]
+    /// 热度值
     pub hot: f64,
-    #[ts_field(10)]
+    /// 排行榜获取时间
     pub rank_time: String,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "limit_step")]
+#[response(api = "limit_step", partial_derive(Debug, Serialize))]
 pub struct LimitStepItem {
-    #[ts_field(0)]
+    /// 股票代码
     pub ts_code: String,
-    #[ts_field(1)]
+    /// 股票名称
     pub name: String,
-    #[ts_field(2)]
-    pub trade_date: String,
-    #[ts_field(3)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 连板次数
     pub nums: String,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "limit_cpt_list")]
+#[response(api = "limit_cpt_list", partial_derive(Debug, Serialize))]
 pub struct LimitCptListItem {
-    #[ts_field(0)]
+    /// 板块代码
     pub ts_code: String,
-    #[ts_field(1)]
+    /// 板块名称
     pub name: String,
-    #[ts_field(2)]
-    pub trade_date: String,
-    #[ts_field(3)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 上榜天数
     pub days: i32,
-    #[ts_field(4)]
+    /// 连板高度
     pub up_stat: String,
-    #[ts_field(5)]
+    /// 连板家数
     pub cons_nums: i32,
-    #[ts_field(6)]
+    /// 涨停家数
     pub up_nums: i32,
-    #[ts_field(7)]
+    /// 涨跌幅%
     pub pct_chg: f64,
-    #[ts_field(8)]
+    /// 板块热点排名
     pub rank: String,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "moneyflow_ths")]
+#[response(api = "moneyflow_ths", partial_derive(Debug, Serialize))]
 pub struct ThsMoneyflowItem {
-    #[ts_field(0)]
-    pub trade_date: String,
-    #[ts_field(1)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 股票代码
     pub ts_code: String,
-    #[ts_field(2)]
+    /// 股票名称
     pub name: String,
-    #[ts_field(3)]
+    /// 涨跌幅
     pub pct_change: f64,
-    #[ts_field(4)]
+    /// 最新价
     pub latest: f64,
-    #[ts_field(5)]
+    /// 资金净流入(万元)
     pub net_amount: f64,
-    #[ts_field(6)]
+    /// 5日主力净额(万元)
     pub net_d5_amount: f64,
-    #[ts_field(7)]
+    /// 今日大单净流入额(万元)
     pub buy_lg_amount: f64,
-    #[ts_field(8)]
+    /// 今日大单净流入占比(%)
     pub buy_lg_amount_rate: f64,
-    #[ts_field(9)]
+    /// 今日中单净流入额(万元)
     pub buy_md_amount: f64,
-    #[ts_field(10)]
+    /// 今日中单净流入占比(%)
     pub buy_md_amount_rate: f64,
-    #[ts_field(11)]
+    /// 今日小单净流入额(万元)
     pub buy_sm_amount: f64,
-    #[ts_field(12)]
+    /// 今日小单净流入占比(%)
     pub buy_sm_amount_rate: f64,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "moneyflow_cnt_ths")]
+#[response(api = "moneyflow_cnt_ths", partial_derive(Debug, Serialize))]
 pub struct ThsMoneyflowCptItem {
-    #[ts_field(0)]
-    pub trade_date: String,
-    #[ts_field(1)]
+    /// 交易日期
+    #[ts_field(with = "yyyymmdd")]
+    pub trade_date: NaiveDate,
+    /// 板块代码
     pub ts_code: String,
-    #[ts_field(2)]
+    /// 板块名称
     pub name: String,
-    #[ts_field(3)]
+    /// 领涨股票名称
     pub lead_stock: String,
-    #[ts_field(4)]
+    /// 最新价
     pub close_price: f64,
-    #[ts_field(5)]
+    /// 行业涨跌幅
     pub pct_change: f64,
-    #[ts_field(6)]
+    /// 板块指数
     pub index_close: f64,
-    #[ts_field(7)]
+    /// 公司数量
     pub company_num: i32,
-    #[ts_field(8)]
+    /// 领涨股涨跌幅
     pub pct_change_stock: f64,
-    #[ts_field(9)]
+    /// 流入资金(元)
     pub net_buy_amount: f64,
-    #[ts_field(10)]
+    /// 流出资金(元)
     pub net_sell_amount: f64,
-    #[ts_field(11)]
+    /// 净额(元)
     pub net_amount: f64,
 }
 
 #[derive(TsResponse, Serialize, Debug)]
-#[response(api = "stk_mins")]
+#[response(api = "stk_mins", partial_derive(Debug, Serialize))]
 pub struct StkMinsItem {
-    #[ts_field(0)]
+    /// 股票代码
     pub ts_code: String,
-    #[ts_field(1)]
-    pub trade_time: String,
-    #[ts_field(2)]
+    /// 交易时间
+    #[ts_field(with = "datetime")]
+    pub trade_time: NaiveDateTime,
+    /// 开盘价
     pub open: f64,
-    #[ts_field(3)]
+    /// 收盘价
     pub close: f64,
-    #[ts_field(4)]
+    /// 最高价
     pub high: f64,
-    #[ts_field(5)]
+    /// 最低价
     pub low: f64,
-    #[ts_field(6)]
+    /// 成交量
     pub vol: i64,
-    #[ts_field(7)]
+    /// 成交金额
     pub amount: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+    use serde_json::json;
+    use ts_derive::TsResponse;
+
+    use crate::*;
+
+    #[test]
+    fn test_from_json_by_name() {
+        // Columns in a different order than the struct fields
+        let json = json!({
+            "code": 0,
+            "msg": "",
+            "data": {
+                "fields": ["nums", "trade_date", "ts_code", "name"],
+                "items": [["2", "20250407", "000001.SZ", "平安银行"]],
+                "has_more": false
+            }
+        });
+
+        let res = LimitStepItem::from_json(&json).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].name, "平安银行");
+        assert_eq!(
+            res[0].trade_date,
+            NaiveDate::from_ymd_opt(2025, 4, 7).unwrap()
+        );
+        assert_eq!(res[0].nums, "2");
+    }
+
+    #[test]
+    fn test_from_json_converters() {
+        let json = json!({
+            "data": {
+                "fields": ["ts_code", "trade_date", "lu_time", "open_time"],
+                "items": [["000001.SZ", "20250407", "09:30:39", ""]]
+            }
+        });
+        let res = KplListItem::from_json(&json).unwrap();
+        assert_eq!(res[0].trade_date, NaiveDate::from_ymd_opt(2025, 4, 7));
+        assert_eq!(res[0].lu_time, NaiveTime::from_hms_opt(9, 30, 39));
+        assert_eq!(res[0].open_time, None);
+        assert_eq!(res[0].ld_time, None);
+
+        let json = json!({
+            "data": {
+                "fields": StkMinsItem::field_names(),
+                "items": [["600000.SH", "2025-04-07 09:31:00", 1.0, 1.0, 1.0, 1.0, 100, 1.0]]
+            }
+        });
+        let res = StkMinsItem::from_json(&json).unwrap();
+        let bytes = serde_json::to_vec(&json).unwrap();
+        let page = RowPage::<StkMinsItem>::decode(&bytes).unwrap();
+        assert_eq!(res[0].trade_time, page.rows[0].trade_time);
+        assert_eq!(
+            res[0].trade_time,
+            NaiveDate::from_ymd_opt(2025, 4, 7)
+                .unwrap()
+                .and_hms_opt(9, 31, 0)
+                .unwrap()
+        );
+
+        // A malformed date in a required field is a parse error
+        let json = json!({
+            "data": {
+                "fields": ["ts_code", "name", "trade_date", "nums"],
+                "items": [["000001.SZ", "平安银行", "2025/04/07", "2"]]
+            }
+        });
+        let err = LimitStepItem::from_json(&json).unwrap_err();
+        assert!(matches!(
+            err,
+            TsError::Parse {
+                field: "trade_date",
+                index: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_try_from_row() {
+        let row = json!({"nums": "2", "trade_date": "20250407", "ts_code": "000001.SZ", "name": "平安银行"});
+        let item = LimitStepItem::try_from(&row).unwrap();
+        assert_eq!(item.ts_code, "000001.SZ");
+        assert_eq!(item.nums, "2");
+
+        let row = json!(["000001.SZ", "平安银行", "20250407", "2"]);
+        let item = LimitStepItem::try_from(&row).unwrap();
+        assert_eq!(item.name, "平安银行");
+
+        let row = json!({"ts_code": "000001.SZ"});
+        let err = LimitStepItem::try_from(&row).unwrap_err();
+        assert!(matches!(err, TsError::MissingColumn { field: "name", .. }));
+
+        assert!(LimitStepItem::try_from(&json!("000001.SZ")).is_err());
+    }
+
+    #[test]
+    fn test_from_json_generic() {
+        fn parse<T: TsRow>(json: &serde_json::Value) -> Result<Vec<T>, TsError> {
+            T::from_json(json)
+        }
+
+        let json =
+            json!({"data": {"fields": ["ts_code", "name", "trade_date", "nums"], "items": []}});
+        assert!(parse::<LimitStepItem>(&json).unwrap().is_empty());
+        assert_eq!(
+            LimitStepItem::field_names(),
+            vec!["ts_code", "name", "trade_date", "nums"]
+        );
+    }
+
+    #[test]
+    fn test_from_json_missing_column() {
+        let json = json!({
+            "data": {
+                "fields": ["ts_code", "name", "trade_date"],
+                "items": [["000001.SZ", "平安银行", "20250407"]]
+            }
+        });
+
+        let err = LimitStepItem::from_json(&json).unwrap_err();
+        assert!(matches!(err, TsError::MissingColumn { field: "nums", .. }));
+
+        // Columns of default fields may be left out
+        let res = KplListItem::from_json(&json).unwrap();
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].pct_chg, 0.0);
+    }
+
+    #[test]
+    fn test_from_json_index_fallback() {
+        #[derive(TsResponse, Debug)]
+        #[response(api = "limit_step", index_fallback)]
+        struct IndexedItem {
+            #[ts_field(0)]
+            ts_code: String,
+            #[ts_field(1)]
+            name: String,
+        }
+
+        let json = json!({"data": {"items": [["000001.SZ", "平安银行"]]}});
+        assert!(LimitStepItem::from_json(&json).is_err());
+
+        let res = IndexedItem::from_json(&json).unwrap();
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].name, "平安银行");
+    }
+
+    #[test]
+    fn test_from_json_rename_and_default_all() {
+        #[derive(TsResponse, Debug)]
+        #[response(api = "stock_basic", default_all)]
+        struct RenamedItem {
+            ts_code: String,
+            #[ts_field(name = "type")]
+            kind: String,
+            r#in: i64,
+        }
+
+        assert_eq!(RenamedItem::field_names(), vec!["ts_code", "type", "in"]);
+
+        let json = json!({
+            "data": {
+                "fields": ["type", "ts_code", "in"],
+                "items": [["A", "000001.SZ", null], [null, "000002.SZ", 3]]
+            }
+        });
+
+        let res = RenamedItem::from_json(&json).unwrap();
+        assert_eq!(res[0].kind, "A");
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].r#in, 0);
+        assert_eq!(res[1].kind, "");
+        assert_eq!(res[1].r#in, 3);
+    }
+
+    #[test]
+    fn test_field_enum() {
+        assert_eq!(LimitStepItemField::TsCode.column_name(), "ts_code");
+        assert_eq!(
+            LimitStepItemField::ALL
+                .iter()
+                .map(|field| field.column_name())
+                .collect::<Vec<_>>(),
+            LimitStepItem::field_names()
+        );
+        assert_eq!(
+            <StkMinsItem as TsRow>::Field::TradeTime.column_name(),
+            "trade_time"
+        );
+    }
+
+    #[test]
+    fn test_from_json_partial() {
+        let json = json!({
+            "data": {
+                "fields": ["trade_date", "ts_code"],
+                "items": [["20250407", "000001.SZ"]]
+            }
+        });
+
+        // Unselected columns are None in the generated partial model
+        assert!(LimitStepItem::from_json(&json).is_err());
+        let res = LimitStepItemPartial::from_json(&json).unwrap();
+        assert_eq!(res[0].ts_code.as_deref(), Some("000001.SZ"));
+        assert_eq!(res[0].trade_date, NaiveDate::from_ymd_opt(2025, 4, 7));
+        assert_eq!(res[0].name, None);
+        // Unselected fields are left out when serialized
+        assert_eq!(
+            serde_json::to_value(&res[0]).unwrap(),
+            json!({"ts_code": "000001.SZ", "trade_date": "2025-04-07"})
+        );
+        assert_eq!(
+            LimitStepItemPartial::field_names(),
+            LimitStepItem::field_names()
+        );
+
+        let bytes = serde_json::to_vec(&json).unwrap();
+        let page = RowPage::<<LimitStepItem as TsRow>::Partial>::decode(&bytes).unwrap();
+        assert_eq!(page.rows[0].nums, None);
+
+        // and defaulted for `default_all` models, which are their own partial model
+        let res = <KplListItem as TsRow>::Partial::from_json(&json).unwrap();
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].name, "");
+    }
+
+    /// A converter target implementing neither `Debug` nor `Serialize`
+    struct Board(String);
+
+    impl convert::Convert for Board {
+        type Target = Board;
+
+        fn parse(value: &str) -> Result<Board, String> {
+            Ok(Board(value.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_partial_of_plain_model() {
+        // Without `partial_derive` the partial model only derives `Default`
+        #[derive(TsResponse)]
+        #[response(api = "plain")]
+        struct PlainItem {
+            ts_code: String,
+            #[ts_field(with = "Board")]
+            board: Board,
+        }
+
+        let json =
+            json!({"data": {"fields": ["ts_code", "board"], "items": [["000001.SZ", "主板"]]}});
+        let res = PlainItem::from_json(&json).unwrap();
+        assert_eq!(res[0].ts_code, "000001.SZ");
+        assert_eq!(res[0].board.0, "主板");
+
+        let json = json!({"data": {"fields": ["board"], "items": [["主板"]]}});
+        let res = PlainItemPartial::from_json(&json).unwrap();
+        assert!(res[0].ts_code.is_none());
+        assert_eq!(
+            res[0].board.as_ref().map(|board| board.0.as_str()),
+            Some("主板")
+        );
+    }
+}
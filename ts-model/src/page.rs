@@ -0,0 +1,125 @@
+use serde_json::Value;
+
+use crate::TsError;
+
+/// Rows requested per call when the endpoint does not set `page_size`
+pub const DEFAULT_PAGE_SIZE: usize = 5000;
+/// Upper bound of rows fetched by one paged query
+pub const DEFAULT_MAX_ROWS: usize = 1_000_000;
+
+/// How a paged query walks through `offset`/`limit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// Rows requested per call, sent as `limit`
+    pub page_size: usize,
+    /// Stop with an error once more rows than this would be fetched
+    pub max_rows: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            max_rows: DEFAULT_MAX_ROWS,
+        }
+    }
+}
+
+impl Pagination {
+    /// Paginate with the given page size and the default row limit
+    pub fn new(page_size: usize) -> Self {
+        Self {
+            page_size: page_size.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Set the page size
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Set the maximum number of rows
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+}
+
+/// Number of rows in `data.items` of a response body
+pub fn page_len(json: &Value) -> usize {
+    json.pointer("/data/items")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len)
+}
+
+/// Whether another page follows the given one
+///
+/// Uses `data.has_more` when present; otherwise a full page is taken to mean
+/// there may be more.
+pub fn has_more(json: &Value, page_size: usize) -> bool {
+    match json.pointer("/data/has_more").and_then(Value::as_bool) {
+        Some(has_more) => has_more,
+        None => page_len(json) >= page_size,
+    }
+}
+
+/// A decoded response page that paged queries can walk through
+///
+/// Implemented for the raw `serde_json::Value` body and for typed
+/// [`RowPage`](crate::RowPage)s.
+pub trait PageBody: Sized + Send + 'static {
+    /// Decode a response body, turning a non-zero `code` into an error
+    fn decode(api_name: &str, body: &[u8]) -> Result<Self, TsError>;
+
+    /// Number of rows in the page
+    fn len(&self) -> usize;
+
+    /// Whether the page has no rows
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether another page follows this one
+    fn has_more(&self, page_size: usize) -> bool;
+}
+
+impl PageBody for Value {
+    fn decode(api_name: &str, body: &[u8]) -> Result<Self, TsError> {
+        let json: Value =
+            serde_json::from_slice(body).map_err(|e| TsError::response(api_name, e.to_string()))?;
+        // Tushare reports business errors with HTTP 200 and a non-zero code
+        TsError::check_code(api_name, &json)?;
+        Ok(json)
+    }
+
+    fn len(&self) -> usize {
+        page_len(self)
+    }
+
+    fn has_more(&self, page_size: usize) -> bool {
+        has_more(self, page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_has_more() {
+        let page = json!({"data": {"fields": ["a"], "items": [[1], [2]], "has_more": true}});
+        assert_eq!(page_len(&page), 2);
+        assert!(has_more(&page, 10));
+
+        let page = json!({"data": {"fields": ["a"], "items": [[1], [2]], "has_more": false}});
+        assert!(!has_more(&page, 2));
+
+        let page = json!({"data": {"fields": ["a"], "items": [[1], [2]]}});
+        assert!(has_more(&page, 2));
+        assert!(!has_more(&page, 3));
+    }
+}
@@ -0,0 +1,252 @@
+//! The generic `tushare_query` tool, calling any API by name
+//!
+//! Which APIs it may reach is controlled by the client's [`QueryPolicy`], read
+//! from `TUSHARE_QUERY_ALLOW` and `TUSHARE_QUERY_DENY` by
+//! [`TsClientBuilder::from_env`](crate::TsClientBuilder::from_env).
+
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    inventory,
+    row::rows_as_dicts,
+    tool::{self, ToolFuture},
+    ToolError, TsClient, TsTool,
+};
+
+/// Which APIs the `tushare_query` tool may call
+///
+/// Entries are API names, or prefixes ending in `*` such as `stk_*`. An API is
+/// allowed when it matches the allowlist (or there is none) and does not match
+/// the denylist.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryPolicy {
+    allow: Option<Vec<String>>,
+    deny: Vec<String>,
+}
+
+impl QueryPolicy {
+    /// Allow every API
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse comma separated allow and deny lists, e.g. from the environment
+    pub fn from_lists(allow: Option<&str>, deny: Option<&str>) -> Self {
+        Self {
+            allow: allow.map(split_list),
+            deny: deny.map(split_list).unwrap_or_default(),
+        }
+    }
+
+    /// Only allow the APIs matching `patterns`
+    pub fn allow<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allow = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Never allow the APIs matching `patterns`
+    pub fn deny<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.deny.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether `api_name` may be called
+    pub fn allows(&self, api_name: &str) -> bool {
+        let allowed = self.allow.as_ref().is_none_or(|allow| {
+            allow
+                .iter()
+                .any(|pattern| matches_pattern(pattern, api_name))
+        });
+        allowed
+            && !self
+                .deny
+                .iter()
+                .any(|pattern| matches_pattern(pattern, api_name))
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn matches_pattern(pattern: &str, api_name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => api_name.starts_with(prefix),
+        None => pattern == api_name,
+    }
+}
+
+/// Arguments of the `tushare_query` tool
+#[derive(Debug, Deserialize, JsonSchema)]
+struct QueryArgs {
+    /// 接口名称, 例如 daily
+    api_name: String,
+    /// 接口参数, 例如 {"ts_code": "000001.SZ", "trade_date": "20250407"}
+    #[serde(default)]
+    params: Map<String, Value>,
+    /// 返回的字段(可选, 默认返回接口的全部字段)
+    #[serde(default)]
+    fields: Option<Vec<String>>,
+}
+
+fn query_description() -> &'static str {
+    static DESCRIPTION: OnceLock<String> = OnceLock::new();
+    DESCRIPTION.get_or_init(|| {
+        tool::tool_description(
+            "调用任意Tushare接口, 以字段名为键返回每一行数据",
+            &[
+                ("api_name", "接口名称, 例如 daily"),
+                (
+                    "params",
+                    "接口参数, 例如 {\"ts_code\": \"000001.SZ\", \"trade_date\": \"20250407\"}",
+                ),
+                ("fields", "返回的字段(可选, 默认返回接口的全部字段)"),
+            ],
+            &[],
+        )
+    })
+}
+
+fn query_schema() -> Value {
+    let schema =
+        tool::schemars::r#gen::SchemaGenerator::default().into_root_schema_for::<QueryArgs>();
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+fn query(client: TsClient, arguments: Value) -> ToolFuture {
+    Box::pin(async move {
+        let args: QueryArgs =
+            serde_json::from_value(arguments).map_err(|e| ToolError::InvalidArguments {
+                tool: "tushare_query",
+                reason: e.to_string(),
+            })?;
+        if !client.query_policy().allows(&args.api_name) {
+            return Err(ToolError::NotAllowed {
+                api_name: args.api_name,
+            });
+        }
+
+        let fields: Option<Vec<&str>> = args
+            .fields
+            .as_ref()
+            .map(|fields| fields.iter().map(String::as_str).collect());
+        let json = client
+            .call(&args.api_name, args.params, fields.as_deref())
+            .await?;
+        let rows = rows_as_dicts(&args.api_name, &json)?;
+
+        Ok(Value::Array(rows.into_iter().map(Value::Object).collect()))
+    })
+}
+
+inventory::submit! {
+    TsTool {
+        name: "tushare_query",
+        description: query_description,
+        input_schema: query_schema,
+        call: query,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{find_tool, MockTransport, RetryPolicy, TsError};
+
+    #[test]
+    fn test_policy() {
+        let policy = QueryPolicy::new();
+        assert!(policy.allows("daily"));
+
+        let policy = QueryPolicy::from_lists(Some("daily, stk_*"), Some("stk_mins"));
+        assert!(policy.allows("daily"));
+        assert!(policy.allows("stk_limit"));
+        assert!(!policy.allows("stk_mins"));
+        assert!(!policy.allows("weekly"));
+
+        let policy = QueryPolicy::new().deny(["stk_*"]);
+        assert!(policy.allows("daily"));
+        assert!(!policy.allows("stk_limit"));
+    }
+
+    #[tokio::test]
+    async fn test_query_tool() {
+        let transport = MockTransport::new().with_response(
+            "daily",
+            json!({"trade_date": "20250407"}),
+            json!({
+                "code": 0,
+                "data": {
+                    "fields": ["ts_code", "close"],
+                    "items": [["000001.SZ", 10.09], ["000002.SZ", 6.78]]
+                }
+            }),
+        );
+        let client = TsClient::builder()
+            .transport(transport.clone())
+            .retry(RetryPolicy::none())
+            .query_policy(QueryPolicy::new().deny(["stk_mins"]))
+            .build()
+            .unwrap();
+        let tool = find_tool("tushare_query").unwrap();
+
+        let rows = (tool.call)(
+            client.clone(),
+            json!({
+                "api_name": "daily",
+                "params": {"trade_date": "20250407"},
+                "fields": ["ts_code", "close"]
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            json!([
+                {"ts_code": "000001.SZ", "close": 10.09},
+                {"ts_code": "000002.SZ", "close": 6.78}
+            ])
+        );
+        assert_eq!(
+            transport.requests()[0].fields,
+            Some(vec!["ts_code".to_string(), "close".to_string()])
+        );
+
+        let err = (tool.call)(client.clone(), json!({"api_name": "stk_mins"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::NotAllowed { .. }));
+        assert_eq!(transport.requests().len(), 1);
+
+        let err = (tool.call)(client.clone(), json!({"api_name": "weekly"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::Ts(TsError::Unmatched { .. })));
+
+        let err = (tool.call)(client, json!({"params": {}}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments { .. }));
+
+        let schema = query_schema();
+        assert_eq!(schema.pointer("/required/0"), Some(&json!("api_name")));
+    }
+}
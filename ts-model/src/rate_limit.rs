@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Calls per minute allowed for an API without an explicit limit
+///
+/// Matches the quota of most interfaces for an account with 2000 points.
+pub const DEFAULT_RATE_LIMIT: u32 = 200;
+
+/// Client-side sliding windows keyed by API name
+///
+/// Tushare counts the calls of the last minute, so no 60s window may hold more
+/// than the limit, not even right after startup. Callers over the quota are
+/// queued instead of failing: every call reserves a start time up front and
+/// then sleeps until it is reached.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<LimiterInner>>,
+}
+
+#[derive(Debug)]
+struct LimiterInner {
+    default: Option<u32>,
+    overrides: HashMap<String, Option<u32>>,
+    windows: HashMap<String, Window>,
+}
+
+/// The length of the window Tushare counts calls in
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Window {
+    per_minute: u32,
+    /// Start times of the last `per_minute` calls, oldest first
+    starts: VecDeque<Instant>,
+}
+
+impl Window {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            starts: VecDeque::with_capacity(per_minute as usize),
+        }
+    }
+
+    /// Reserve the earliest start that keeps the last minute within the limit
+    /// and return how long to wait for it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let start = if self.starts.len() < self.per_minute as usize {
+            now
+        } else {
+            // The call `per_minute` calls back must have left the window
+            let oldest = self.starts.pop_front().unwrap_or(now);
+            now.max(oldest + WINDOW)
+        };
+        self.starts.push_back(start);
+        start - now
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(Some(DEFAULT_RATE_LIMIT))
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `default` calls per minute for every API, `None` meaning unlimited
+    pub fn new(default: Option<u32>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LimiterInner {
+                default,
+                overrides: HashMap::new(),
+                windows: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Override the calls per minute of one API, `None` meaning unlimited
+    ///
+    /// Takes precedence over both the endpoint's own limit and the default.
+    pub fn set_limit(&self, api_name: impl Into<String>, per_minute: Option<u32>) {
+        let api_name = api_name.into();
+        let mut inner = self.inner.lock().unwrap();
+        inner.windows.remove(&api_name);
+        inner.overrides.insert(api_name, per_minute);
+    }
+
+    /// The calls per minute that apply to an API
+    pub fn limit_for(&self, api_name: &str, endpoint_limit: Option<u32>) -> Option<u32> {
+        let inner = self.inner.lock().unwrap();
+        inner.limit_for(api_name, endpoint_limit)
+    }
+
+    /// Wait until a call to `api_name` is allowed
+    ///
+    /// `endpoint_limit` is the limit declared on the endpoint, if any.
+    pub async fn acquire(&self, api_name: &str, endpoint_limit: Option<u32>) {
+        let wait = {
+            let mut inner = self.inner.lock().unwrap();
+            let Some(per_minute) = inner.limit_for(api_name, endpoint_limit) else {
+                return;
+            };
+
+            let window = inner
+                .windows
+                .entry(api_name.to_string())
+                .or_insert_with(|| Window::new(per_minute));
+            if window.per_minute != per_minute {
+                *window = Window::new(per_minute);
+            }
+            window.reserve(Instant::now())
+        };
+
+        if !wait.is_zero() {
+            tracing::info!(
+                api_name,
+                wait_ms = wait.as_millis() as u64,
+                "rate limit reached, waiting"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl LimiterInner {
+    fn limit_for(&self, api_name: &str, endpoint_limit: Option<u32>) -> Option<u32> {
+        let limit = match self.overrides.get(api_name) {
+            Some(limit) => *limit,
+            None => endpoint_limit.or(self.default),
+        };
+        limit.filter(|limit| *limit > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for() {
+        let limiter = RateLimiter::new(Some(200));
+        assert_eq!(limiter.limit_for("kpl_list", None), Some(200));
+        assert_eq!(limiter.limit_for("stk_mins", Some(30)), Some(30));
+
+        limiter.set_limit("stk_mins", Some(500));
+        assert_eq!(limiter.limit_for("stk_mins", Some(30)), Some(500));
+
+        limiter.set_limit("stk_mins", None);
+        assert_eq!(limiter.limit_for("stk_mins", Some(30)), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_queues() {
+        let limiter = RateLimiter::new(Some(2));
+        let start = Instant::now();
+
+        limiter.acquire("kpl_list", None).await;
+        limiter.acquire("kpl_list", None).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // Other APIs have their own window
+        limiter.acquire("ths_hot", None).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // The third call waits for the first to leave the window
+        limiter.acquire("kpl_list", None).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_within_window() {
+        let limiter = RateLimiter::new(Some(5));
+        let start = Instant::now();
+
+        let calls = (0..23).map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter.acquire("kpl_list", None).await;
+                start.elapsed()
+            })
+        });
+        let mut granted = Vec::new();
+        for call in calls {
+            granted.push(call.await.unwrap());
+        }
+        granted.sort();
+        assert_eq!(granted.last(), Some(&Duration::from_secs(240)));
+
+        // No 60s window starting at a granted call holds more than the limit
+        for from in &granted {
+            let in_window = granted
+                .iter()
+                .filter(|at| **at >= *from && **at < *from + WINDOW)
+                .count();
+            assert!(in_window <= 5, "{} calls after {:?}", in_window, from);
+        }
+    }
+}
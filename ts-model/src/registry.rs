@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{inventory, tool::ToolFuture, ToolError, TsClient, TsTool};
+
+/// A request param of an endpoint
+#[derive(Debug, Serialize)]
+pub struct ParamInfo {
+    /// The param name sent to Tushare
+    pub name: &'static str,
+    /// The Rust type of the field
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    /// Whether the param must be given, i.e. it is neither an `Option` nor `#[serde(default)]`
+    pub required: bool,
+    /// The doc comment of the field
+    pub doc: &'static str,
+}
+
+/// A column of a response model
+#[derive(Debug, Serialize)]
+pub struct FieldInfo {
+    /// The column name in `data.fields`
+    pub name: &'static str,
+    /// The Rust type of the field
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    /// The doc comment of the field
+    pub doc: &'static str,
+}
+
+/// A response model, registered by `#[derive(TsResponse)]`
+#[derive(Debug, Serialize)]
+pub struct ResponseInfo {
+    /// The name of the model struct
+    pub name: &'static str,
+    /// The API the rows are returned by
+    pub api_name: &'static str,
+    /// The columns, in field order
+    pub fields: &'static [FieldInfo],
+}
+
+inventory::collect!(ResponseInfo);
+
+/// An endpoint, registered by `#[derive(TsEndpoint)]`
+#[derive(Debug, Serialize)]
+pub struct EndpointInfo {
+    /// The name of the request struct
+    pub name: &'static str,
+    /// The Tushare `api_name`
+    pub api_name: &'static str,
+    /// Human readable description of the endpoint
+    pub description: &'static str,
+    /// The request params, in field order
+    pub params: &'static [ParamInfo],
+    /// The model the rows are parsed into, `None` for untyped endpoints
+    pub response: Option<&'static ResponseInfo>,
+    /// The MCP tool the endpoint is exposed as, if any
+    pub tool: Option<&'static str>,
+}
+
+inventory::collect!(EndpointInfo);
+
+/// Every endpoint in the build, sorted by `api_name` and then by name
+pub fn endpoints() -> Vec<&'static EndpointInfo> {
+    let mut endpoints: Vec<_> = inventory::iter::<EndpointInfo>().collect();
+    endpoints.sort_by_key(|endpoint| (endpoint.api_name, endpoint.name));
+    endpoints
+}
+
+/// Every response model in the build, sorted by name
+pub fn responses() -> Vec<&'static ResponseInfo> {
+    let mut responses: Vec<_> = inventory::iter::<ResponseInfo>().collect();
+    responses.sort_by_key(|response| response.name);
+    responses
+}
+
+/// Arguments of the `list_endpoints` tool
+#[derive(Debug, Default, Deserialize)]
+struct ListEndpointsArgs {
+    #[serde(default)]
+    api_name: Option<String>,
+}
+
+fn list_endpoints_description() -> &'static str {
+    "列出支持的全部Tushare接口，以及每个接口的参数和返回字段\n\n\
+     # Arguments\n\
+     * `api_name` - 只列出该接口(可选)"
+}
+
+fn list_endpoints_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "api_name": {
+                "description": "只列出该接口(可选)",
+                "type": "string"
+            }
+        }
+    })
+}
+
+fn list_endpoints(_client: TsClient, arguments: Value) -> ToolFuture {
+    Box::pin(async move {
+        let args = match arguments {
+            Value::Null => ListEndpointsArgs::default(),
+            arguments => {
+                serde_json::from_value(arguments).map_err(|e| ToolError::InvalidArguments {
+                    tool: "list_endpoints",
+                    reason: e.to_string(),
+                })?
+            }
+        };
+
+        let endpoints: Vec<_> = endpoints()
+            .into_iter()
+            .filter(|endpoint| {
+                args.api_name
+                    .as_deref()
+                    .is_none_or(|api_name| endpoint.api_name == api_name)
+            })
+            .collect();
+        Ok(json!(endpoints))
+    })
+}
+
+inventory::submit! {
+    TsTool {
+        name: "list_endpoints",
+        description: list_endpoints_description,
+        input_schema: list_endpoints_schema,
+        call: list_endpoints,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_tool;
+
+    #[test]
+    fn test_endpoints() {
+        let endpoints = endpoints();
+        let stk_mins = endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == "StkMinsReq")
+            .unwrap();
+        assert_eq!(stk_mins.api_name, "stk_mins");
+        assert_eq!(stk_mins.tool, Some("stk_mins"));
+
+        let params: Vec<_> = stk_mins
+            .params
+            .iter()
+            .map(|param| (param.name, param.ty, param.required))
+            .collect();
+        assert_eq!(
+            params,
+            [
+                ("ts_code", "String", true),
+                ("freq", "String", true),
+                ("start_date", "Option<String>", false),
+                ("end_date", "Option<String>", false),
+            ]
+        );
+
+        let response = stk_mins.response.unwrap();
+        assert_eq!(response.name, "StkMinsItem");
+        assert_eq!(response.fields[1].name, "trade_time");
+        assert_eq!(response.fields[1].ty, "NaiveDateTime");
+        assert_eq!(response.fields[1].doc, "交易时间");
+
+        // Endpoints sharing an API are listed next to each other
+        let limit_step: Vec<_> = endpoints
+            .iter()
+            .filter(|endpoint| endpoint.api_name == "limit_step")
+            .map(|endpoint| endpoint.name)
+            .collect();
+        assert_eq!(limit_step, ["HisLimitStepReq", "LimitStepReq"]);
+
+        assert!(responses()
+            .iter()
+            .any(|response| response.name == "KplListItem" && response.fields.len() == 24));
+    }
+
+    #[tokio::test]
+    async fn test_list_endpoints_tool() {
+        let client = TsClient::new("token").unwrap();
+        let tool = find_tool("list_endpoints").unwrap();
+
+        let all = (tool.call)(client.clone(), Value::Null).await.unwrap();
+        assert!(all.as_array().unwrap().len() >= 11);
+
+        let kpl_list = (tool.call)(client.clone(), json!({"api_name": "kpl_list"}))
+            .await
+            .unwrap();
+        assert_eq!(kpl_list.as_array().unwrap().len(), 1);
+        assert_eq!(kpl_list[0]["params"][0]["name"], "tag");
+        assert_eq!(
+            kpl_list[0]["response"]["fields"][2]["type"],
+            "Option<NaiveDate>"
+        );
+
+        assert!((tool.call)(client, json!({"api_name": 1})).await.is_err());
+    }
+}
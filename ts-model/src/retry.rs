@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use crate::TsError;
+
+/// Retries after the first attempt unless configured otherwise
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Delay before the first retry
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound of the delay between two attempts
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Delay before retrying a rate limited call, the length of Tushare's window
+pub const DEFAULT_RATE_LIMIT_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff applied to transient failures
+///
+/// Only errors for which [`TsError::is_retryable`] holds are retried, so
+/// permission, token and parameter errors fail immediately. Rate limited calls
+/// are not backed off but retried once the per-minute window has passed, as any
+/// earlier attempt would only be rejected again and count against the quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every further one
+    pub base_delay: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_delay: Duration,
+    /// Randomize each delay between half and all of its value
+    pub jitter: bool,
+    /// Delay before retrying a [`TsError::RateLimited`] call
+    pub rate_limit_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: true,
+            rate_limit_delay: DEFAULT_RATE_LIMIT_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of retries
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Set the upper bound of the delay
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Enable or disable jitter
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the delay before retrying a rate limited call
+    pub fn rate_limit_delay(mut self, rate_limit_delay: Duration) -> Self {
+        self.rate_limit_delay = rate_limit_delay;
+        self
+    }
+
+    /// Delay before the given retry, counting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+
+        if self.jitter {
+            delay.mul_f64(0.5 + fastrand::f64() * 0.5)
+        } else {
+            delay
+        }
+    }
+
+    /// Delay before the given retry of a call that failed with `err`
+    pub fn delay_for(&self, err: &TsError, retry: u32) -> Duration {
+        match err {
+            TsError::RateLimited { .. } => self.rate_limit_delay,
+            _ => self.delay(retry),
+        }
+    }
+
+    /// Whether a failed attempt should be retried
+    pub fn should_retry(&self, err: &TsError, retry: u32) -> bool {
+        retry <= self.max_retries && err.is_retryable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::default()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(500))
+            .jitter(false);
+
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+
+        let rate_limited =
+            TsError::from_code("stk_mins", 40203, "抱歉，您每分钟最多访问该接口500次");
+        assert_eq!(policy.delay_for(&rate_limited, 1), DEFAULT_RATE_LIMIT_DELAY);
+        let unavailable = TsError::Status {
+            api_name: "stk_mins".to_string(),
+            status: reqwest::StatusCode::BAD_GATEWAY,
+        };
+        assert_eq!(
+            policy.delay_for(&unavailable, 2),
+            Duration::from_millis(200)
+        );
+
+        let policy = policy.jitter(true);
+        for retry in 1..5 {
+            let delay = policy.delay(retry);
+            let max = policy.jitter(false).delay(retry);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let policy = RetryPolicy::default().max_retries(2);
+        let rate_limited =
+            TsError::from_code("stk_mins", 40203, "抱歉，您每分钟最多访问该接口500次");
+        let no_permission = TsError::from_code("stk_mins", 40203, "抱歉，您没有访问该接口的权限");
+
+        assert!(policy.should_retry(&rate_limited, 1));
+        assert!(policy.should_retry(&rate_limited, 2));
+        assert!(!policy.should_retry(&rate_limited, 3));
+        assert!(!policy.should_retry(&no_permission, 1));
+        assert!(!RetryPolicy::none().should_retry(&rate_limited, 1));
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Deserializer;
+use serde_json::{Map, Value};
+
+use crate::TsError;
+
+/// A model parsed from the rows of a Tushare response, implemented by `#[derive(TsResponse)]`
+///
+/// Columns are resolved once per response with [`TsRow::resolve_columns`] and
+/// then every row is parsed with [`TsRow::from_row`], or cell by cell straight
+/// from the body bytes with [`TsRow::deserialize_cell`] and [`TsRow::build`].
+pub trait TsRow: Sized {
+    /// The API the rows are returned by
+    fn api_name() -> &'static str;
+
+    /// The column names requested for the model, in field order
+    fn field_names() -> Vec<&'static str>;
+
+    /// Find the position of every field's column in `data.fields`
+    ///
+    /// Returns one entry per field in field order, `None` for optional columns
+    /// that are absent. `fields` is `None` when the response lists no columns.
+    fn resolve_columns(fields: Option<&[&str]>) -> Result<Vec<Option<usize>>, TsError>;
+
+    /// Parse one row of `data.items` using the positions from [`TsRow::resolve_columns`]
+    fn from_row(columns: &[Option<usize>], row: &[Value]) -> Result<Self, TsError>;
+
+    /// The field selector generated as `{Model}Field`
+    type Field: TsField;
+
+    /// The model returned when only some fields are selected
+    ///
+    /// A generated `{Model}Partial` with every field optional, or the model
+    /// itself for `#[response(default_all)]` where unselected fields default.
+    type Partial: TsRow<Field = Self::Field>;
+
+    /// Holds the cells of one row while it is being deserialized by [`RowPage::decode`]
+    ///
+    /// [`RowPage::decode`]: crate::RowPage::decode
+    type Builder: Default;
+
+    /// Deserialize the cell of field number `field` (in field order) into the builder
+    fn deserialize_cell<'de, D: Deserializer<'de>>(
+        builder: &mut Self::Builder,
+        field: usize,
+        cell: D,
+    ) -> Result<(), D::Error>;
+
+    /// Finish a row, applying the same null/default rules as [`TsRow::from_row`]
+    fn build(builder: Self::Builder, columns: &[Option<usize>]) -> Result<Self, TsError>;
+
+    /// Parse every row of a response body
+    fn from_json(json: &Value) -> Result<Vec<Self>, TsError> {
+        let api_name = Self::api_name();
+        TsError::check_code(api_name, json)?;
+
+        let data = json
+            .get("data")
+            .ok_or_else(|| TsError::response(api_name, "Missing 'data' field in response"))?;
+
+        let fields: Option<Vec<&str>> = match data.get("fields") {
+            Some(fields) => Some(
+                fields
+                    .as_array()
+                    .ok_or_else(|| TsError::response(api_name, "'fields' is not an array"))?
+                    .iter()
+                    .map(|field| field.as_str().unwrap_or_default())
+                    .collect(),
+            ),
+            None => None,
+        };
+        let columns = Self::resolve_columns(fields.as_deref())?;
+
+        let items = data
+            .get("items")
+            .ok_or_else(|| TsError::response(api_name, "Missing 'items' field in data"))?
+            .as_array()
+            .ok_or_else(|| TsError::response(api_name, "'items' is not an array"))?;
+
+        items
+            .iter()
+            .map(|item| {
+                let row = item
+                    .as_array()
+                    .ok_or_else(|| TsError::response(api_name, "Item is not an array"))?;
+                Self::from_row(&columns, row)
+            })
+            .collect()
+    }
+}
+
+/// A column of a [`TsRow`] model, generated as `{Model}Field` by `#[derive(TsResponse)]`
+pub trait TsField: Copy + 'static {
+    /// The column name requested in `fields`
+    fn column_name(self) -> &'static str;
+}
+
+/// Parse a single row given either as an object keyed by column name or as an
+/// array in [`TsRow::field_names`] order
+///
+/// Backs the `TryFrom<&Value>` impl generated by `#[derive(TsResponse)]`.
+pub fn row_from_value<T: TsRow>(row: &Value) -> Result<T, TsError> {
+    match row {
+        Value::Object(map) => {
+            let fields: Vec<&str> = map.keys().map(String::as_str).collect();
+            let values: Vec<Value> = map.values().cloned().collect();
+            let columns = T::resolve_columns(Some(&fields))?;
+            T::from_row(&columns, &values)
+        }
+        Value::Array(values) => {
+            let fields = T::field_names();
+            let columns = T::resolve_columns(Some(&fields))?;
+            T::from_row(&columns, values)
+        }
+        _ => Err(TsError::response(
+            T::api_name(),
+            "row is neither an object nor an array",
+        )),
+    }
+}
+
+/// Turn the `data.items` of a response body into records keyed by `data.fields`
+///
+/// Backs `execute_as_dicts` and the `tushare_query` tool.
+pub fn rows_as_dicts(api_name: &str, json: &Value) -> Result<Vec<Map<String, Value>>, TsError> {
+    // Extract fields and items
+    let data = json
+        .get("data")
+        .ok_or_else(|| TsError::response(api_name, "Missing 'data' field in response"))?;
+
+    let fields = data
+        .get("fields")
+        .ok_or_else(|| TsError::response(api_name, "Missing 'fields' field in data"))?
+        .as_array()
+        .ok_or_else(|| TsError::response(api_name, "'fields' is not an array"))?;
+
+    let items = data
+        .get("items")
+        .ok_or_else(|| TsError::response(api_name, "Missing 'items' field in data"))?
+        .as_array()
+        .ok_or_else(|| TsError::response(api_name, "'items' is not an array"))?;
+
+    let mut result = Vec::with_capacity(items.len());
+
+    for item_value in items {
+        let item = item_value
+            .as_array()
+            .ok_or_else(|| TsError::response(api_name, "Item is not an array"))?;
+
+        let mut map = Map::new();
+
+        // Map fields to values
+        for (field, value) in fields.iter().zip(item) {
+            let field_name = field
+                .as_str()
+                .ok_or_else(|| TsError::response(api_name, "Field name is not a string"))?;
+            map.insert(field_name.to_string(), value.clone());
+        }
+
+        result.push(map);
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,59 @@
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+
+use crate::{RowPage, TsError};
+
+/// A boxed stream of pages or rows produced by a paged query
+pub type TsStream<T> = BoxStream<'static, Result<T, TsError>>;
+
+/// Flatten a stream of typed pages into a stream of rows
+pub fn page_rows<T>(pages: TsStream<RowPage<T>>) -> TsStream<T>
+where
+    T: Send + 'static,
+{
+    pages
+        .map_ok(|page| stream::iter(page.rows.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+}
+
+/// A stream yielding a single error, for failures before the first request
+pub fn error<T>(err: TsError) -> TsStream<T>
+where
+    T: Send + 'static,
+{
+    stream::once(async move { Err(err) }).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(rows: Vec<i64>, has_more: bool) -> Result<RowPage<i64>, TsError> {
+        Ok(RowPage {
+            rows,
+            has_more: Some(has_more),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_page_rows() {
+        let pages: TsStream<RowPage<i64>> =
+            stream::iter(vec![page(vec![1, 2], true), page(vec![3], false)]).boxed();
+
+        let res: Vec<i64> = page_rows(pages).try_collect().await.unwrap();
+        assert_eq!(res, vec![1, 2, 3]);
+
+        let pages: TsStream<RowPage<i64>> = stream::iter(vec![
+            page(vec![1], true),
+            Err(TsError::response("test", "boom")),
+        ])
+        .boxed();
+
+        let res: Vec<Result<i64, TsError>> = page_rows(pages).collect().await;
+        assert_eq!(res.len(), 2);
+        assert!(matches!(res[1], Err(TsError::Response { .. })));
+    }
+}
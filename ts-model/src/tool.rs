@@ -0,0 +1,268 @@
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{TsApi, TsClient, TsError};
+
+// Re-exported for the `#[endpoint(tool = "...")]` derive output
+#[doc(hidden)]
+pub use schemars;
+
+/// The future returned by a tool call
+pub type ToolFuture = BoxFuture<'static, Result<Value, ToolError>>;
+
+/// An MCP tool generated from an endpoint marked with `#[endpoint(tool = "...")]`
+///
+/// Every such endpoint registers one entry; see [`tools`] and [`find_tool`].
+pub struct TsTool {
+    /// Tool name exposed to the MCP client
+    pub name: &'static str,
+    /// Description built from the endpoint, its params and its response fields
+    pub description: fn() -> &'static str,
+    /// JSON schema of the tool arguments
+    pub input_schema: fn() -> Value,
+    /// Run the endpoint with JSON arguments and return the rows as JSON
+    pub call: fn(TsClient, Value) -> ToolFuture,
+}
+
+inventory::collect!(TsTool);
+
+/// Why a tool call failed
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    /// The arguments do not match the tool's input schema
+    #[error("invalid arguments for {tool}: {reason}")]
+    InvalidArguments { tool: &'static str, reason: String },
+
+    /// The API is not allowed by the client's `QueryPolicy`
+    #[error("calling '{api_name}' is not allowed")]
+    NotAllowed { api_name: String },
+
+    /// The Tushare request itself failed
+    #[error(transparent)]
+    Ts(#[from] TsError),
+}
+
+/// A failed tool call as reported to the MCP client
+///
+/// Sent as the content of a result with `isError` set, so the model can tell a
+/// failure from an empty result and decide whether calling again may help.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolErrorReport {
+    /// A stable name for the kind of failure, e.g. `rate_limited`
+    pub kind: &'static str,
+    /// A short Chinese summary followed by the full English error
+    pub message: String,
+    /// The Tushare business `code`, when Tushare returned one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    /// The Tushare API the failure belongs to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_name: Option<String>,
+    /// Whether the same call may succeed when retried later
+    pub retryable: bool,
+}
+
+impl ToolError {
+    /// Describe the failure for the MCP client
+    pub fn report(&self) -> ToolErrorReport {
+        let (kind, summary) = self.kind();
+        let (code, api_name, retryable) = match self {
+            ToolError::InvalidArguments { .. } => (None, None, false),
+            ToolError::NotAllowed { api_name } => (None, Some(api_name.clone()), false),
+            ToolError::Ts(err) => (
+                err.code(),
+                err.api_name().map(String::from),
+                err.is_retryable(),
+            ),
+        };
+
+        ToolErrorReport {
+            kind,
+            message: format!("{}: {}", summary, self),
+            code,
+            api_name,
+            retryable,
+        }
+    }
+
+    fn kind(&self) -> (&'static str, &'static str) {
+        let err = match self {
+            ToolError::InvalidArguments { .. } => return ("invalid_arguments", "参数错误"),
+            ToolError::NotAllowed { .. } => return ("not_allowed", "不允许调用该接口"),
+            ToolError::Ts(err) => err,
+        };
+
+        match err {
+            TsError::MissingToken => ("missing_token", "未配置TUSHARE_TOKEN"),
+            TsError::Client(_) | TsError::Http { .. } | TsError::Status { .. } => {
+                ("network", "网络请求失败")
+            }
+            TsError::Api { .. } => ("api", "接口返回错误"),
+            TsError::Permission { .. } => ("permission", "没有该接口的权限"),
+            TsError::RateLimited { .. } => ("rate_limited", "调用频率超限, 请稍后重试"),
+            TsError::QuotaExhausted { .. } => ("quota_exhausted", "今日调用次数已用完, 请明天再试"),
+            TsError::InsufficientPoints { .. } => ("insufficient_points", "积分不足"),
+            TsError::InvalidToken { .. } => ("invalid_token", "token无效"),
+            TsError::Params { .. } | TsError::InvalidParams { .. } => {
+                ("invalid_params", "参数错误")
+            }
+            TsError::Response { .. } | TsError::MissingColumn { .. } | TsError::Parse { .. } => {
+                ("invalid_response", "响应数据无法解析")
+            }
+            TsError::TooManyRows { .. } => ("too_many_rows", "返回数据过多, 请缩小查询范围"),
+            TsError::Unmatched { .. } => ("unmatched", "没有录制该请求"),
+            TsError::Cassette { .. } => ("cassette", "录制文件读写失败"),
+        }
+    }
+}
+
+/// Run endpoint `E` as tool `tool` with JSON arguments
+///
+/// Missing arguments (`null`) are treated as an empty object so endpoints
+/// whose params all have defaults can be called without any.
+pub fn call<E>(tool: &'static str, client: TsClient, arguments: Value) -> ToolFuture
+where
+    E: TsApi + DeserializeOwned + Send + Sync + 'static,
+    E::Response: Serialize,
+{
+    Box::pin(async move {
+        let arguments = match arguments {
+            Value::Null => Value::Object(Default::default()),
+            arguments => arguments,
+        };
+        let request: E =
+            serde_json::from_value(arguments).map_err(|e| ToolError::InvalidArguments {
+                tool,
+                reason: e.to_string(),
+            })?;
+
+        let rows = request.fetch(&client).await?;
+        serde_json::to_value(rows).map_err(|e| TsError::response(E::API_NAME, e.to_string()).into())
+    })
+}
+
+/// All registered tools, sorted by name
+pub fn tools() -> Vec<&'static TsTool> {
+    let mut tools: Vec<_> = inventory::iter::<TsTool>().collect();
+    tools.sort_by_key(|tool| tool.name);
+    tools
+}
+
+/// Look up a registered tool by name
+pub fn find_tool(name: &str) -> Option<&'static TsTool> {
+    inventory::iter::<TsTool>().find(|tool| tool.name == name)
+}
+
+/// Render a tool description in the `# Arguments` / `# Returns` layout
+///
+/// `args` and `returns` are `(name, doc)` pairs; entries without docs are listed
+/// by name only.
+pub fn tool_description(desc: &str, args: &[(&str, &str)], returns: &[(&str, &str)]) -> String {
+    let mut out = desc.to_string();
+
+    if !args.is_empty() {
+        out.push_str("\n\n# Arguments");
+        for (name, doc) in args {
+            out.push_str(&format!("\n* `{}`", name));
+            if !doc.is_empty() {
+                out.push_str(&format!(" - {}", doc));
+            }
+        }
+    }
+
+    if !returns.is_empty() {
+        out.push_str("\n\n# Returns");
+        for (name, doc) in returns {
+            out.push_str(&format!("\n- `{}`", name));
+            if !doc.is_empty() {
+                out.push_str(&format!(": {}", doc));
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tools_registered() {
+        let names: Vec<_> = tools().iter().map(|tool| tool.name).collect();
+        assert!(names.contains(&"kpl_list"));
+        assert!(names.contains(&"stk_mins"));
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let tool = find_tool("kpl_list").unwrap();
+        let description = (tool.description)();
+        assert!(description.starts_with("获取涨跌停板数据"));
+        assert!(description.contains("* `tag` - "));
+        assert!(description.contains("- `lu_desc`: 涨停原因"));
+
+        let schema = (tool.input_schema)();
+        assert!(schema.pointer("/properties/trade_date").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let client = TsClient::new("token").unwrap();
+        let tool = find_tool("stk_mins").unwrap();
+        let err = (tool.call)(client, serde_json::json!({"freq": 1}))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ToolError::InvalidArguments {
+                tool: "stk_mins",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_error_report() {
+        let err = ToolError::from(TsError::from_code(
+            "kpl_list",
+            40203,
+            "抱歉，您每分钟最多访问该接口200次",
+        ));
+        let report = err.report();
+        assert_eq!(report.kind, "rate_limited");
+        assert_eq!(report.code, Some(40203));
+        assert_eq!(report.api_name.as_deref(), Some("kpl_list"));
+        assert!(report.retryable);
+        assert!(report.message.starts_with("调用频率超限"));
+        assert!(report
+            .message
+            .contains("rate limit exceeded for 'kpl_list'"));
+
+        let report = ToolError::from(TsError::from_code("kpl_list", 2002, "没有权限")).report();
+        assert_eq!(report.kind, "permission");
+        assert!(!report.retryable);
+
+        let report = ToolError::from(TsError::from_code(
+            "kpl_list",
+            40203,
+            "抱歉，您每天最多访问该接口10000次",
+        ))
+        .report();
+        assert_eq!(report.kind, "quota_exhausted");
+        assert!(!report.retryable);
+
+        let report = ToolError::InvalidArguments {
+            tool: "stk_mins",
+            reason: "missing field `ts_code`".to_string(),
+        }
+        .report();
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "kind": "invalid_arguments",
+                "message": "参数错误: invalid arguments for stk_mins: missing field `ts_code`",
+                "retryable": false,
+            })
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::TsError;
+
+/// A Tushare request as handed to a [`Transport`], without the token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TsRequest {
+    /// The Tushare `api_name`
+    pub api_name: String,
+    /// The request params, including `offset`/`limit` for paged calls
+    pub params: Map<String, Value>,
+    /// The requested columns, `None` for all of them
+    pub fields: Option<Vec<String>>,
+}
+
+/// Sends a single request and returns the raw response body
+///
+/// [`TsClient`](crate::TsClient) uses [`HttpTransport`] unless another one is
+/// set with [`TsClientBuilder::transport`](crate::TsClientBuilder::transport).
+/// Rate limiting, retries and decoding stay in the client.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send `request` once
+    fn send<'a>(&'a self, request: &'a TsRequest) -> BoxFuture<'a, Result<Vec<u8>, TsError>>;
+}
+
+/// Posts requests to the Tushare HTTP API
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    http: reqwest::Client,
+    token: String,
+    base_url: String,
+}
+
+impl HttpTransport {
+    /// Create a transport posting to `base_url` with `token`
+    pub fn new(
+        http: reqwest::Client,
+        token: impl Into<String>,
+        base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            token: token.into(),
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send<'a>(&'a self, request: &'a TsRequest) -> BoxFuture<'a, Result<Vec<u8>, TsError>> {
+        Box::pin(async move {
+            let api_name = &request.api_name;
+
+            // Create request body
+            let mut request_body = Map::new();
+            request_body.insert("api_name".to_string(), Value::String(api_name.clone()));
+            request_body.insert("token".to_string(), Value::String(self.token.clone()));
+            request_body.insert("params".to_string(), Value::Object(request.params.clone()));
+
+            // Add fields if provided
+            if let Some(fields) = &request.fields {
+                request_body.insert("fields".to_string(), Value::String(fields.join(",")));
+            }
+
+            let response = self
+                .http
+                .post(&self.base_url)
+                .json(&request_body)
+                .send()
+                .await
+                .map_err(|source| TsError::Http {
+                    api_name: api_name.clone(),
+                    source,
+                })?;
+
+            if !response.status().is_success() {
+                return Err(TsError::Status {
+                    api_name: api_name.clone(),
+                    status: response.status(),
+                });
+            }
+
+            let body = response.bytes().await.map_err(|source| TsError::Http {
+                api_name: api_name.clone(),
+                source,
+            })?;
+            Ok(body.into())
+        })
+    }
+}
+
+/// An in-memory transport answering `(api_name, params)` with canned bodies
+///
+/// Params are matched exactly, falling back to a match without `offset` and
+/// `limit` so a single-page response also answers the paged methods. Unknown
+/// requests fail with [`TsError::Unmatched`]. Clones share the responses and
+/// the log of [`MockTransport::requests`].
+///
+/// ```ignore
+/// let transport = MockTransport::new().with_response(
+///     "kpl_concept",
+///     json!({"trade_date": "20250407"}),
+///     json!({"code": 0, "data": {"fields": [..], "items": [..]}}),
+/// );
+/// let client = TsClient::builder().transport(transport).build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: HashMap<(String, String), Value>,
+    requests: Vec<TsRequest>,
+}
+
+impl MockTransport {
+    /// Create a transport without any responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `api_name` called with `params` (a JSON object) with `body`
+    pub fn with_response(self, api_name: impl Into<String>, params: Value, body: Value) -> Self {
+        self.insert(api_name, params, body);
+        self
+    }
+
+    /// Add or replace the response to `api_name` called with `params`
+    pub fn insert(&self, api_name: impl Into<String>, params: Value, body: Value) {
+        let params = match params {
+            Value::Object(params) => params,
+            _ => Map::new(),
+        };
+        let key = (api_name.into(), params_key(&params));
+        self.state.lock().unwrap().responses.insert(key, body);
+    }
+
+    /// Every request sent so far, oldest first
+    pub fn requests(&self) -> Vec<TsRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn respond(&self, request: &TsRequest) -> Result<Vec<u8>, TsError> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(request.clone());
+
+        let mut unpaged = request.params.clone();
+        unpaged.remove("offset");
+        unpaged.remove("limit");
+
+        let exact = (request.api_name.clone(), params_key(&request.params));
+        let fallback = (request.api_name.clone(), params_key(&unpaged));
+        let body = state
+            .responses
+            .get(&exact)
+            .or_else(|| state.responses.get(&fallback))
+            .ok_or_else(|| TsError::Unmatched {
+                api_name: request.api_name.clone(),
+                params: exact.1.clone(),
+            })?;
+
+        serde_json::to_vec(body).map_err(|e| TsError::response(&request.api_name, e.to_string()))
+    }
+}
+
+impl Transport for MockTransport {
+    fn send<'a>(&'a self, request: &'a TsRequest) -> BoxFuture<'a, Result<Vec<u8>, TsError>> {
+        Box::pin(async move { self.respond(request) })
+    }
+}
+
+/// Params serialized with sorted keys, so equal params always match
+pub(crate) fn params_key(params: &Map<String, Value>) -> String {
+    let sorted: BTreeMap<&String, &Value> = params.iter().collect();
+    serde_json::to_string(&sorted).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request(params: Value) -> TsRequest {
+        TsRequest {
+            api_name: "kpl_concept".to_string(),
+            params: params.as_object().cloned().unwrap_or_default(),
+            fields: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport() {
+        let transport = MockTransport::new().with_response(
+            "kpl_concept",
+            json!({"trade_date": "20250407", "src": "kpl"}),
+            json!({"code": 0}),
+        );
+
+        // Key order does not matter and offset/limit fall back to the unpaged response
+        let body = transport
+            .send(&request(
+                json!({"src": "kpl", "offset": 0, "limit": 5000, "trade_date": "20250407"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(body, br#"{"code":0}"#);
+
+        let err = transport
+            .send(&request(json!({"trade_date": "20250408"})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TsError::Unmatched { .. }));
+        assert_eq!(err.api_name(), Some("kpl_concept"));
+
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(transport.requests()[1].params["trade_date"], "20250408");
+    }
+}